@@ -0,0 +1,282 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Result;
+use futures_util::{stream, Stream, StreamExt};
+
+use crate::{
+    asyncreq::{politeness::Politeness, retry::RetryPolicy, Requester},
+    config::Config,
+    parser,
+    parser::brand::BrandList,
+    parser::vector::Vector,
+    warc::{Archive, WarcWriter},
+};
+
+/// Entrypoint for using the parser as a library.
+/// Cheap to clone, clones share the connection pool and request permits.
+#[derive(Clone, Debug)]
+pub struct Analyzer {
+    requester: Requester,
+    config: Arc<Config>,
+}
+
+/// Builds an [`Analyzer`], unset options fall back to [`Config::default`]
+#[derive(Debug, Default)]
+pub struct AnalyzerBuilder {
+    client: Option<reqwest::Client>,
+    unverified_client: Option<reqwest::Client>,
+    archive: Option<Arc<Archive>>,
+    recorder: Option<Arc<WarcWriter>>,
+    config: Config,
+}
+
+impl AnalyzerBuilder {
+    /// Use an existing client instead of creating one.
+    /// Redirects are followed by the analyzer, so it should be built with
+    /// [`reqwest::redirect::Policy::none`], and with `tls_info(true)` for certificate features.
+    /// Its user agent should be [`Config::user_agent`], which robots.txt is matched against.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Client retrying requests whose certificate fails verification, used when
+    /// [`AnalyzerBuilder::accept_invalid_certs`] is set. It should be built like
+    /// [`AnalyzerBuilder::client`] with `danger_accept_invalid_certs(true)`.
+    /// One is created when unset, unless a client is given, since its settings cannot be copied.
+    pub fn unverified_client(mut self, client: reqwest::Client) -> Self {
+        self.unverified_client = Some(client);
+        self
+    }
+
+    /// User agent for the created clients, and whose product token robots.txt is matched against
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.config.user_agent = user_agent.into();
+        self
+    }
+
+    /// Replace every option at once
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Urls analysed in parallel by the batch and stream variants
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.config.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Hyprlinks probed in parallel per page
+    pub fn link_concurrency(mut self, link_concurrency: usize) -> Self {
+        self.config.link_concurrency = link_concurrency.max(1);
+        self
+    }
+
+    /// Requests in flight across every analysis
+    pub fn max_requests(mut self, max_requests: usize) -> Self {
+        self.config.max_requests = max_requests.max(1);
+        self
+    }
+
+    /// Timeout for fetching the page itself
+    pub fn page_timeout(mut self, timeout: Duration) -> Self {
+        self.config.page_timeout = timeout;
+        self
+    }
+
+    /// Timeout for probing each hyprlink
+    pub fn link_timeout(mut self, timeout: Duration) -> Self {
+        self.config.link_timeout = timeout;
+        self
+    }
+
+    /// Whether discovered hyprlinks are fetched
+    pub fn probe_hyprlinks(mut self, probe_hyprlinks: bool) -> Self {
+        self.config.probe_hyprlinks = probe_hyprlinks;
+        self
+    }
+
+    /// Redirects followed before giving up on a url
+    pub fn max_redirects(mut self, max_redirects: usize) -> Self {
+        self.config.max_redirects = max_redirects;
+        self
+    }
+
+    /// Requests in flight to a single host
+    pub fn host_concurrency(mut self, host_concurrency: usize) -> Self {
+        self.config.host_concurrency = host_concurrency.max(1);
+        self
+    }
+
+    /// Requests started per second to a single host, 0 for unlimited
+    pub fn host_rate_limit(mut self, requests_per_second: f64) -> Self {
+        self.config.host_rate_limit = requests_per_second.max(0.0);
+        self
+    }
+
+    /// Whether urls disallowed by the host's robots.txt are skipped
+    pub fn respect_robots(mut self, respect_robots: bool) -> Self {
+        self.config.respect_robots = respect_robots;
+        self
+    }
+
+    /// Whether requests failing certificate verification are sent again accepting
+    /// the certificate, so pages with self-signed or mismatched certificates are analysed
+    pub fn accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.config.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    /// Retries of a request answered with 429 or 503, waiting out `Retry-After`
+    /// when it is no longer than `max_retry_after`
+    pub fn rate_limit_retries(mut self, retries: usize, max_retry_after: Duration) -> Self {
+        self.config.rate_limit_retries = retries;
+        self.config.max_retry_after = max_retry_after;
+        self
+    }
+
+    /// Retries of a request that failed to connect or was cut off,
+    /// backing off exponentially from `backoff`
+    pub fn retries(mut self, retries: usize, backoff: Duration) -> Self {
+        self.config.retries = retries;
+        self.config.retry_backoff = backoff;
+        self
+    }
+
+    /// Bytes of the page, and of each hyprlink, read before they are truncated
+    pub fn max_body_bytes(mut self, page: usize, link: usize) -> Self {
+        self.config.max_page_bytes = page;
+        self.config.max_link_bytes = link;
+        self
+    }
+
+    /// Whether hyprlinks to images, media and archives are probed with HEAD
+    pub fn head_probes(mut self, head_probes: bool) -> Self {
+        self.config.head_probes = head_probes;
+        self
+    }
+
+    /// Follows same-site links up to `depth` clicks from the page, scanning at most `pages` of them
+    pub fn crawl(mut self, depth: usize, pages: usize) -> Self {
+        self.config.crawl_depth = depth;
+        self.config.crawl_pages = pages;
+        self
+    }
+
+    /// Whether meta refreshes are followed as redirects
+    pub fn follow_meta_refresh(mut self, follow_meta_refresh: bool) -> Self {
+        self.config.follow_meta_refresh = follow_meta_refresh;
+        self
+    }
+
+    /// Brands pages are checked for impersonation against
+    pub fn brands(mut self, brands: BrandList) -> Self {
+        self.config.brands = Arc::new(brands);
+        self
+    }
+
+    /// Replay every response from an archive instead of fetching it
+    pub fn archive(mut self, archive: Archive) -> Self {
+        self.archive = Some(Arc::new(archive));
+        self
+    }
+
+    /// Append every fetched response to a web archive, ignored when replaying
+    pub fn record(mut self, recorder: WarcWriter) -> Self {
+        self.recorder = Some(Arc::new(recorder));
+        self
+    }
+
+    pub fn build(self) -> Result<Analyzer> {
+        let client_builder = || {
+            reqwest::Client::builder()
+                .redirect(reqwest::redirect::Policy::none())
+                .tls_info(true)
+                .user_agent(&self.config.user_agent)
+        };
+        // The unverified client must be built like the one it stands in for
+        let unverified_client = match (self.unverified_client, &self.client) {
+            _ if !self.config.accept_invalid_certs => None,
+            (Some(client), _) => Some(client),
+            (None, None) => Some(client_builder().danger_accept_invalid_certs(true).build()?),
+            (None, Some(_)) => {
+                anyhow::bail!("accepting invalid certificates with a custom client needs an unverified client")
+            }
+        };
+        let client = match self.client {
+            Some(client) => client,
+            None => client_builder().build()?,
+        };
+
+        let mut requester = Requester::new(client, self.config.max_requests)
+            .with_politeness(Politeness::new(&self.config))
+            .with_retry_policy(RetryPolicy::new(&self.config));
+        if let Some(client) = unverified_client {
+            requester = requester.with_unverified_client(client);
+        }
+        if let Some(archive) = self.archive {
+            requester = requester.with_archive(archive);
+        }
+        if let Some(recorder) = self.recorder {
+            requester = requester.with_recorder(recorder);
+        }
+
+        Ok(Analyzer {
+            requester,
+            config: Arc::new(self.config),
+        })
+    }
+}
+
+impl Analyzer {
+    pub fn builder() -> AnalyzerBuilder {
+        AnalyzerBuilder::default()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Generates the vector for a single url
+    pub async fn analyze(&self, url: &str) -> Result<Vector> {
+        parser::generate_vector(
+            self.requester.clone(),
+            Arc::clone(&self.config),
+            url.to_string(),
+        )
+        .await
+    }
+
+    /// Analyses urls in parallel, yielding results as they complete
+    pub fn analyze_stream<I>(&self, urls: I) -> impl Stream<Item = (String, Result<Vector>)>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let analyzer = self.clone();
+        stream::iter(urls)
+            .map(move |url| {
+                let analyzer = analyzer.clone();
+                async move {
+                    let handle = tokio::spawn({
+                        let url = url.clone();
+                        async move { analyzer.analyze(&url).await }
+                    });
+                    let result = match handle.await {
+                        Ok(result) => result,
+                        Err(e) => Err(anyhow::anyhow!("analysis task failed: {e}")),
+                    };
+                    (url, result)
+                }
+            })
+            .buffer_unordered(self.config.concurrency)
+    }
+
+    /// Analyses urls in parallel, returning results in completion order
+    pub async fn analyze_batch<I>(&self, urls: I) -> Vec<(String, Result<Vector>)>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        self.analyze_stream(urls).collect().await
+    }
+}
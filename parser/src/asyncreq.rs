@@ -1,13 +1,682 @@
-use anyhow::Result;
+use std::{sync::Arc, time::Duration};
 
-use reqwest::{RequestBuilder, Response};
+use anyhow::Result;
+use bytes::{Bytes, BytesMut};
+use futures_util::StreamExt;
+use reqwest::{tls::TlsInfo, Method, RequestBuilder, Response, ResponseBuilderExt};
 use tokio::sync::Semaphore;
+use url::Url;
+
+use crate::{
+    config::Config,
+    parser::failure::{classify_reqwest, FailureStage},
+    warc::{writer::Capture, Archive, WarcWriter},
+};
+
+use politeness::Politeness;
+use retry::{Attempts, RetryPolicy};
+use robots::{Robots, RobotsDisallowedError};
+
+pub mod politeness;
+pub mod retry;
+pub mod robots;
+
+/// Bytes of robots.txt parsed, crawlers must read at least 500 KiB, see RFC 9309
+const MAX_ROBOTS_BYTES: usize = 500 * 1024;
+
+/// Set as a response extension when its certificate failed verification
+/// and was accepted on a second attempt with the unverified client
+#[derive(Debug, Clone, Copy)]
+pub struct CertificateInvalid;
+
+/// Shared client, request permits and per-host limits
+#[derive(Clone, Debug)]
+pub struct Requester {
+    pub client: reqwest::Client,
+    /// When set, requests failing certificate verification are sent again with it,
+    /// so pages with invalid certificates are still analysed
+    unverified_client: Option<reqwest::Client>,
+    permits: Arc<Semaphore>,
+    politeness: Arc<Politeness>,
+    retry: RetryPolicy,
+    /// When set, responses are replayed from it instead of fetched
+    archive: Option<Arc<Archive>>,
+    /// When set, every exchange, failed attempts and robots.txt included, is written to it
+    recorder: Option<Arc<WarcWriter>>,
+}
+
+impl Requester {
+    pub fn new(client: reqwest::Client, max_requests: usize) -> Self {
+        Requester {
+            client,
+            unverified_client: None,
+            permits: Arc::new(Semaphore::new(max_requests)),
+            politeness: Arc::new(Politeness::new(&Config::default())),
+            retry: RetryPolicy::new(&Config::default()),
+            archive: None,
+            recorder: None,
+        }
+    }
+
+    /// Replaces the default per-host limits
+    pub fn with_politeness(mut self, politeness: Politeness) -> Self {
+        self.politeness = Arc::new(politeness);
+        self
+    }
+
+    /// Replaces the default retry policy
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Retries requests that fail certificate verification with a client accepting
+    /// invalid certificates, built with `danger_accept_invalid_certs(true)`
+    pub fn with_unverified_client(mut self, client: reqwest::Client) -> Self {
+        self.unverified_client = Some(client);
+        self
+    }
+
+    /// Replays every request from the archive, nothing is fetched
+    pub fn with_archive(mut self, archive: Arc<Archive>) -> Self {
+        self.archive = Some(archive);
+        self
+    }
+
+    /// Records every request sent, and its response or error, to the archive
+    pub fn with_recorder(mut self, recorder: Arc<WarcWriter>) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Make a request in async.
+    /// This will acquire a permit and release it after the request is done.
+    pub async fn make_req(&self, req: RequestBuilder) -> Result<Response, reqwest::Error> {
+        let _permit = self.permits.acquire().await.unwrap();
+        req.send().await
+    }
+
+    /// Gets a url, from the archive when replaying.
+    /// Live requests respect the per-host limits and robots.txt, back off on 429 and 503,
+    /// and are retried on transient errors. The response carries its [`Attempts`].
+    /// While recording, at most `body_limit` bytes of the body are read and archived.
+    pub async fn get(&self, url: &Url, timeout: Duration, body_limit: usize) -> Result<Response> {
+        self.request(Method::GET, url, timeout, body_limit).await
+    }
+
+    /// Heads a url, for its headers only.
+    /// Replays answer with the archived HEAD, or the archived get when there is none.
+    pub async fn head(&self, url: &Url, timeout: Duration) -> Result<Response> {
+        self.request(Method::HEAD, url, timeout, 0).await
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        url: &Url,
+        timeout: Duration,
+        body_limit: usize,
+    ) -> Result<Response> {
+        if let Some(archive) = &self.archive {
+            let mut response = archive.response(url, &method)?;
+            response.extensions_mut().insert(Attempts {
+                count: 1,
+                last_error: None,
+            });
+            return Ok(response);
+        }
+
+        if !self.robots(url, timeout).await.is_allowed(url) {
+            return Err(RobotsDisallowedError(url.to_string()).into());
+        }
+
+        let (mut response, attempts) = self.fetch(method, url, timeout, body_limit).await?;
+        response.extensions_mut().insert(attempts);
+        Ok(response)
+    }
+
+    /// Fetches a url within the host's limits, retrying while it is rate limited
+    /// or the request fails in a way that may not happen again.
+    /// A certificate that fails verification is accepted on a second attempt when an
+    /// unverified client is set, the response is then marked [`CertificateInvalid`].
+    /// Every attempt is written to the recorder, including the failed ones.
+    async fn fetch(
+        &self,
+        method: Method,
+        url: &Url,
+        timeout: Duration,
+        body_limit: usize,
+    ) -> Result<(Response, Attempts)> {
+        let mut attempts = Attempts::default();
+        let mut rate_limited = 0;
+        let mut failed = 0;
+        let mut client = &self.client;
+        loop {
+            let request = client
+                .request(method.clone(), url.clone())
+                .timeout(timeout)
+                .build()?;
+            let request_headers = request.headers().clone();
+            attempts.count += 1;
+            let result = {
+                let _host_permit = self.politeness.acquire(url).await;
+                self.make_req(RequestBuilder::from_parts(client.clone(), request))
+                    .await
+            };
+
+            match result {
+                Ok(response) => {
+                    let certificate_invalid = !std::ptr::eq(client, &self.client);
+                    let mut response = match &self.recorder {
+                        Some(recorder) => {
+                            let exchange = Exchange {
+                                method: &method,
+                                url,
+                                request_headers: &request_headers,
+                                certificate_invalid,
+                            };
+                            record(recorder, exchange, response, body_limit).await?
+                        }
+                        None => response,
+                    };
+                    if certificate_invalid {
+                        response.extensions_mut().insert(CertificateInvalid);
+                    }
+                    let Some(delay) = self.politeness.backoff(&response, rate_limited) else {
+                        return Ok((response, attempts));
+                    };
+                    self.politeness.pause(url, delay);
+                    rate_limited += 1;
+                }
+                Err(e) => {
+                    if let Some(recorder) = &self.recorder {
+                        recorder
+                            .capture_error(&method, url, &request_headers, &error_chain(&e))
+                            .await?;
+                    }
+                    let kind = classify_reqwest(&e);
+                    // Not a failure of the page, certificate_invalid says why it was sent again
+                    if let (FailureStage::Tls, Some(unverified)) = (kind, &self.unverified_client) {
+                        if !std::ptr::eq(client, unverified) {
+                            client = unverified;
+                            continue;
+                        }
+                    }
+                    attempts.last_error = Some(kind);
+                    let Some(delay) = self.retry.backoff(kind, failed) else {
+                        return Err(anyhow::Error::from(e).context(attempts));
+                    };
+                    tokio::time::sleep(delay).await;
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    /// Robots.txt rules for the url's origin, allowing everything when robots.txt
+    /// is ignored, missing or could not be fetched, and nothing when it fails with 5xx,
+    /// see RFC 9309. A host that cannot be reached fails the url's own request instead.
+    async fn robots(&self, url: &Url, timeout: Duration) -> Robots {
+        let Some(cell) = self.politeness.robots_cell(url) else {
+            return Robots::allow_all();
+        };
+
+        cell.get_or_init(|| async {
+            let Ok(robots_url) = url.join("/robots.txt") else {
+                return Robots::allow_all();
+            };
+            let fetched = self
+                .fetch(Method::GET, &robots_url, timeout, MAX_ROBOTS_BYTES)
+                .await;
+            let Ok((response, _)) = fetched else {
+                return Robots::allow_all();
+            };
+            let status = response.status();
+            if status.is_server_error() {
+                return Robots::disallow_all();
+            }
+            if !status.is_success() {
+                return Robots::allow_all();
+            }
+            // Only the start of a larger robots.txt is parsed
+            match read_body(response, MAX_ROBOTS_BYTES).await {
+                Ok((body, _)) => Robots::parse(
+                    &String::from_utf8_lossy(&body),
+                    self.politeness.robots_token(),
+                ),
+                Err(_) => Robots::allow_all(),
+            }
+        })
+        .await
+        .clone()
+    }
+}
+
+/// Reads at most `limit` bytes of the body, and whether there was more
+pub async fn read_body(response: Response, limit: usize) -> Result<(Bytes, bool)> {
+    let partial = read_partial_body(response, limit).await;
+    match partial.error {
+        Some(e) => Err(e.into()),
+        None => Ok((partial.body, partial.truncated)),
+    }
+}
+
+/// Body read up to a limit, with the error that cut it short if any
+struct PartialBody {
+    body: Bytes,
+    /// There was more than the limit
+    truncated: bool,
+    error: Option<reqwest::Error>,
+}
+
+async fn read_partial_body(response: Response, limit: usize) -> PartialBody {
+    let mut stream = response.bytes_stream();
+    let mut body = BytesMut::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                return PartialBody {
+                    body: body.freeze(),
+                    truncated: false,
+                    error: Some(e),
+                }
+            }
+        };
+        let remaining = limit - body.len();
+        if chunk.len() > remaining {
+            body.extend_from_slice(&chunk[..remaining]);
+            return PartialBody {
+                body: body.freeze(),
+                truncated: true,
+                error: None,
+            };
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    PartialBody {
+        body: body.freeze(),
+        truncated: false,
+        error: None,
+    }
+}
+
+/// Error and its sources, as one line
+fn error_chain(e: &(dyn std::error::Error + 'static)) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(err) = source {
+        message.push_str(&format!(": {err}"));
+        source = err.source();
+    }
+    message
+}
+
+/// Request a response answers
+struct Exchange<'a> {
+    method: &'a Method,
+    url: &'a Url,
+    request_headers: &'a http::HeaderMap,
+    /// Sent again accepting a certificate that failed verification
+    certificate_invalid: bool,
+}
+
+/// Reads the body, up to the limit, to archive it, then hands back an equivalent response.
+/// A body cut off by an error is archived as far as it was read, and the response
+/// handed back fails after it, as the original would have.
+async fn record(
+    recorder: &Arc<WarcWriter>,
+    exchange: Exchange<'_>,
+    response: Response,
+    body_limit: usize,
+) -> Result<Response> {
+    let status = response.status();
+    let headers = response.headers().clone();
+    let mut builder = http::Response::builder()
+        .status(status)
+        .version(response.version())
+        .url(response.url().clone());
+    let tls_info = response.extensions().get::<TlsInfo>().cloned();
+    let partial = read_partial_body(response, body_limit).await;
+    let truncated = match (&partial.error, partial.truncated) {
+        (Some(_), _) => Some("disconnect"),
+        (None, true) => Some("length"),
+        (None, false) => None,
+    };
+
+    recorder
+        .capture(Capture {
+            method: exchange.method,
+            url: exchange.url,
+            request_headers: exchange.request_headers,
+            status,
+            headers: &headers,
+            body: &partial.body,
+            truncated,
+            peer_certificate: tls_info.as_ref().and_then(TlsInfo::peer_certificate),
+            certificate_invalid: exchange.certificate_invalid,
+        })
+        .await?;
+
+    if let Some(response_headers) = builder.headers_mut() {
+        *response_headers = headers;
+    }
+    if let Some(tls_info) = tls_info {
+        builder = builder.extension(tls_info);
+    }
+    let body = match partial.error {
+        Some(e) => {
+            reqwest::Body::wrap_stream(futures_util::stream::iter([Ok(partial.body), Err(e)]))
+        }
+        None => reqwest::Body::from(partial.body),
+    };
+    Ok(builder.body(body)?.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    use reqwest::Method;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+    use url::Url;
+
+    use crate::{
+        config::{Config, DEFAULT_USER_AGENT},
+        parser::failure::FailureStage,
+        warc::{writer::TRUNCATED_HEADER, Archive, RecordReader, WarcWriter},
+        Analyzer,
+    };
+
+    use super::{politeness::Politeness, Requester};
+
+    /// Serves robots.txt, and a page rate limited on its first request
+    async fn serve() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let page_requests = Arc::new(AtomicUsize::new(0));
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let read = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]);
+                let (status, body) = match request.split(' ').nth(1).unwrap_or("/") {
+                    "/robots.txt" => ("200 OK", "User-agent: *\nAllow: /\n"),
+                    _ if page_requests.fetch_add(1, Ordering::SeqCst) == 0 => {
+                        ("429 Too Many Requests", "slow down")
+                    }
+                    _ => ("200 OK", "<p>page</p>"),
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\nretry-after: 0\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn records_robots_and_every_attempt() {
+        let port = serve().await;
+        let path = std::env::temp_dir().join(format!("sharkalyze-record-{port}.warc"));
+        let config = Config {
+            respect_robots: true,
+            ..Config::default()
+        };
+        let requester = Requester::new(reqwest::Client::new(), 4)
+            .with_politeness(Politeness::new(&config))
+            .with_recorder(Arc::new(WarcWriter::create(&path).unwrap()));
+
+        let url = Url::parse(&format!("http://127.0.0.1:{port}/")).unwrap();
+        let response = requester
+            .get(&url, std::time::Duration::from_secs(5), 1024)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        drop(requester);
+
+        let archive = Archive::open(std::slice::from_ref(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(archive.get(&url.join("/robots.txt").unwrap()).is_some());
+        // The rate limited attempt is archived too, the final response replaces it
+        assert_eq!(archive.get(&url).unwrap().status, 200);
+    }
+
+    /// Serves the robots.txt and pages, keeping the user agents seen
+    async fn serve_robots(
+        robots_status: &'static str,
+        robots: String,
+    ) -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let user_agents = Arc::new(Mutex::new(vec![]));
+
+        let seen = Arc::clone(&user_agents);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let read = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                if let Some(user_agent) = request
+                    .lines()
+                    .find_map(|line| line.strip_prefix("user-agent: "))
+                {
+                    seen.lock().unwrap().push(user_agent.to_string());
+                }
+                let (status, body) = match request.split(' ').nth(1) {
+                    Some("/robots.txt") => (robots_status, robots.as_str()),
+                    _ => ("200 OK", "<p>page</p>"),
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\ncontent-type: text/html\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (port, user_agents)
+    }
+
+    #[tokio::test]
+    async fn robots_txt_is_matched_against_the_user_agent_sent() {
+        let (port, user_agents) = serve_robots(
+            "200 OK",
+            "User-agent: sharkalyze\nDisallow: /private\n".to_string(),
+        )
+        .await;
+        let private = format!("http://127.0.0.1:{port}/private");
+
+        let analyzer = Analyzer::builder()
+            .respect_robots(true)
+            .probe_hyprlinks(false)
+            .build()
+            .unwrap();
+        let error = analyzer.analyze(&private).await.unwrap_err();
+        assert_eq!(FailureStage::of(&error), FailureStage::Disallowed);
+        assert_eq!(*user_agents.lock().unwrap(), [DEFAULT_USER_AGENT]);
+
+        let analyzer = Analyzer::builder()
+            .user_agent("OtherBot/1.0")
+            .respect_robots(true)
+            .probe_hyprlinks(false)
+            .build()
+            .unwrap();
+        analyzer.analyze(&private).await.unwrap();
+        // Its robots.txt, then the page
+        assert_eq!(user_agents.lock().unwrap()[1..], ["OtherBot/1.0"; 2]);
+    }
+
+    fn respecting_robots() -> Analyzer {
+        Analyzer::builder()
+            .respect_robots(true)
+            .probe_hyprlinks(false)
+            .retries(0, std::time::Duration::ZERO)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn unreachable_host_fails_with_its_own_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let error = respecting_robots()
+            .analyze(&format!("http://127.0.0.1:{port}/"))
+            .await
+            .unwrap_err();
+        assert_eq!(FailureStage::of(&error), FailureStage::Connect);
+    }
+
+    #[tokio::test]
+    async fn server_error_on_robots_txt_disallows() {
+        let (port, _) = serve_robots("503 Service Unavailable", String::new()).await;
+        let analyzer = Analyzer::builder()
+            .respect_robots(true)
+            .probe_hyprlinks(false)
+            .rate_limit_retries(0, std::time::Duration::ZERO)
+            .build()
+            .unwrap();
+
+        let error = analyzer
+            .analyze(&format!("http://127.0.0.1:{port}/"))
+            .await
+            .unwrap_err();
+        assert_eq!(FailureStage::of(&error), FailureStage::Disallowed);
+    }
+
+    #[tokio::test]
+    async fn only_the_start_of_robots_txt_is_parsed() {
+        let robots = format!(
+            "User-agent: *\n{}\nDisallow: /\n",
+            "#".repeat(super::MAX_ROBOTS_BYTES)
+        );
+        let (port, _) = serve_robots("200 OK", robots).await;
+
+        respecting_robots()
+            .analyze(&format!("http://127.0.0.1:{port}/"))
+            .await
+            .unwrap();
+    }
+
+    /// Serves a page linking to an image and a long text, and a page cut off part way,
+    /// keeping the method of every request
+    async fn serve_recorded() -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        let seen = Arc::clone(&requests);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let read = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]).to_string();
+                let mut parts = request.split(' ');
+                let (method, path) = (parts.next().unwrap_or_default(), parts.next());
+                seen.lock()
+                    .unwrap()
+                    .push(format!("{method} {}", path.unwrap_or("/")));
+
+                let response = match path {
+                    Some("/image.png") => {
+                        let body = match method {
+                            "HEAD" => String::new(),
+                            _ => "x".repeat(5000),
+                        };
+                        format!("HTTP/1.1 200 OK\r\ncontent-type: image/png\r\ncontent-length: 5000\r\nconnection: close\r\n\r\n{body}")
+                    }
+                    Some("/long") => format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\ncontent-length: 100\r\nconnection: close\r\n\r\n{}",
+                        "y".repeat(100)
+                    ),
+                    // Promises more than it sends
+                    Some("/cut") => "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-length: 100\r\nconnection: close\r\n\r\n<p>cut</p>".to_string(),
+                    _ => {
+                        let body = r#"<img src="/image.png"><a href="/long">long</a>"#;
+                        format!(
+                            "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                            body.len()
+                        )
+                    }
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (port, requests)
+    }
+
+    #[tokio::test]
+    async fn records_what_the_scan_reads() {
+        let (port, requests) = serve_recorded().await;
+        let path = std::env::temp_dir().join(format!("sharkalyze-reads-{port}.warc"));
+        let analyzer = |recorder: Option<WarcWriter>| {
+            let mut builder = Analyzer::builder().max_body_bytes(1024, 16);
+            if let Some(recorder) = recorder {
+                builder = builder.record(recorder);
+            }
+            builder.build().unwrap()
+        };
+        let recording = analyzer(Some(WarcWriter::create(&path).unwrap()));
+
+        recording
+            .analyze(&format!("http://127.0.0.1:{port}/"))
+            .await
+            .unwrap();
+        // HEAD probes stay HEAD
+        assert!(requests
+            .lock()
+            .unwrap()
+            .contains(&"HEAD /image.png".to_string()));
+        assert!(!requests
+            .lock()
+            .unwrap()
+            .contains(&"GET /image.png".to_string()));
+
+        // A body cut off part way is a partial page, recorded or not
+        let cut = format!("http://127.0.0.1:{port}/cut");
+        let recorded = recording.analyze(&cut).await.unwrap();
+        let live = analyzer(None).analyze(&cut).await.unwrap();
+        assert_eq!(recorded.html_length, "<p>cut</p>".len());
+        assert_eq!(recorded.html_length, live.html_length);
+        drop(recording);
 
-static PERMITS: Semaphore = Semaphore::const_new(500);
+        let truncated = RecordReader::open(&path)
+            .unwrap()
+            .map(Result::unwrap)
+            .filter_map(|record| {
+                let reason = record.header(TRUNCATED_HEADER)?.to_string();
+                Some((record.target_uri()?.to_string(), reason))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            truncated,
+            [
+                (
+                    format!("http://127.0.0.1:{port}/long"),
+                    "length".to_string()
+                ),
+                (cut.clone(), "disconnect".to_string()),
+            ]
+        );
 
-/// Make a request in async.
-/// This will acquire a permit and release it after the request is done.
-pub async fn make_req(req: RequestBuilder) -> Result<Response, reqwest::Error> {
-    let _permit = PERMITS.acquire().await.unwrap();
-    req.send().await
+        let archive = Archive::open(std::slice::from_ref(&path)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let url = |path: &str| Url::parse(&format!("http://127.0.0.1:{port}{path}")).unwrap();
+        // Hyprlinks are read up to their own limit
+        assert_eq!(archive.get(&url("/long")).unwrap().body.len(), 16);
+        assert!(archive.get(&url("/image.png")).is_none());
+        let head = archive.response(&url("/image.png"), &Method::HEAD).unwrap();
+        assert_eq!(head.headers()["content-length"], "5000");
+    }
 }
@@ -0,0 +1,282 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+use tokio::{
+    sync::{OnceCell, OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+use url::Url;
+
+use crate::config::Config;
+
+use super::robots::{self, Robots};
+
+/// Backoff before the first retry of a 429 or 503 without `Retry-After`,
+/// doubled on every attempt
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Hosts tracked before the idle ones are forgotten
+const MAX_HOSTS: usize = 10_000;
+
+/// Robots.txt kept before the least recently used half is forgotten
+const MAX_ROBOTS: usize = 10_000;
+
+#[derive(Debug)]
+struct HostState {
+    permits: Arc<Semaphore>,
+    /// Earliest time the next request to the host may start
+    next_slot: Instant,
+}
+
+impl HostState {
+    /// No request holds or waits for a permit, and the host may be sent one at once,
+    /// so forgetting it changes nothing
+    fn is_idle(&self) -> bool {
+        Arc::strong_count(&self.permits) == 1 && self.next_slot <= Instant::now()
+    }
+}
+
+#[derive(Debug)]
+struct RobotsEntry {
+    cell: Arc<OnceCell<Robots>>,
+    last_used: Instant,
+}
+
+/// Per-host limits, so a page linking to hundreds of urls on one site
+/// does not hammer it
+#[derive(Debug)]
+pub struct Politeness {
+    host_concurrency: usize,
+    /// Spacing between requests to one host, none when unlimited
+    min_interval: Option<Duration>,
+    respect_robots: bool,
+    /// Product token of our user agent, robots.txt groups naming it apply to us
+    robots_token: String,
+    rate_limit_retries: usize,
+    max_retry_after: Duration,
+    hosts: Mutex<HashMap<String, HostState>>,
+    max_hosts: usize,
+    /// Keyed by origin, robots.txt is fetched once per origin while it is kept
+    robots: Mutex<HashMap<String, RobotsEntry>>,
+    max_robots: usize,
+}
+
+impl Politeness {
+    pub fn new(config: &Config) -> Self {
+        Politeness {
+            host_concurrency: config.host_concurrency.max(1),
+            min_interval: (config.host_rate_limit > 0.0)
+                .then(|| Duration::from_secs_f64(1.0 / config.host_rate_limit)),
+            respect_robots: config.respect_robots,
+            robots_token: robots::product_token(&config.user_agent).to_string(),
+            rate_limit_retries: config.rate_limit_retries,
+            max_retry_after: config.max_retry_after,
+            hosts: Mutex::new(HashMap::new()),
+            max_hosts: MAX_HOSTS,
+            robots: Mutex::new(HashMap::new()),
+            max_robots: MAX_ROBOTS,
+        }
+    }
+
+    /// Waits for a free slot on the url's host, held until the permit is dropped
+    pub async fn acquire(&self, url: &Url) -> OwnedSemaphorePermit {
+        let permits = self.with_host(url, |state| Arc::clone(&state.permits));
+        let permit = permits.acquire_owned().await.unwrap();
+
+        // Rate limited after the permit, so time spent waiting for one does not use up the slot
+        let start = self.with_host(url, |state| {
+            let start = state.next_slot.max(Instant::now());
+            if let Some(interval) = self.min_interval {
+                state.next_slot = start + interval;
+            }
+            start
+        });
+        tokio::time::sleep_until(start).await;
+
+        permit
+    }
+
+    fn with_host<T>(&self, url: &Url, f: impl FnOnce(&mut HostState) -> T) -> T {
+        let mut hosts = self.hosts.lock().unwrap();
+        let host = url.host_str().unwrap_or_default();
+        if hosts.len() >= self.max_hosts && !hosts.contains_key(host) {
+            hosts.retain(|_, state| !state.is_idle());
+        }
+
+        let state = hosts.entry(host.to_string()).or_insert_with(|| HostState {
+            permits: Arc::new(Semaphore::new(self.host_concurrency)),
+            next_slot: Instant::now(),
+        });
+        f(state)
+    }
+
+    /// Holds back every request to the url's host for a while
+    pub fn pause(&self, url: &Url, delay: Duration) {
+        self.with_host(url, |state| {
+            state.next_slot = state.next_slot.max(Instant::now() + delay);
+        });
+    }
+
+    /// How long to back off before retrying, none when the response should be kept
+    pub fn backoff(&self, response: &Response, attempt: usize) -> Option<Duration> {
+        let status = response.status();
+        if attempt >= self.rate_limit_retries
+            || !matches!(
+                status,
+                StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE
+            )
+        {
+            return None;
+        }
+
+        let delay = match response.headers().get(RETRY_AFTER) {
+            Some(value) => retry_after(value.to_str().ok()?)?,
+            None => BASE_BACKOFF * 2u32.saturating_pow(attempt as u32),
+        };
+        // Waits longer than we are willing to give up on the url
+        (delay <= self.max_retry_after).then_some(delay)
+    }
+
+    /// Product token robots.txt groups are matched against
+    pub fn robots_token(&self) -> &str {
+        &self.robots_token
+    }
+
+    /// Robots.txt cache entry for the url's origin, none when robots.txt is ignored
+    pub fn robots_cell(&self, url: &Url) -> Option<Arc<OnceCell<Robots>>> {
+        if !self.respect_robots {
+            return None;
+        }
+
+        let mut robots = self.robots.lock().unwrap();
+        let origin = url.origin().ascii_serialization();
+        if robots.len() >= self.max_robots && !robots.contains_key(&origin) {
+            forget_least_recent(&mut robots);
+        }
+
+        let entry = robots.entry(origin).or_insert_with(|| RobotsEntry {
+            cell: Arc::default(),
+            last_used: Instant::now(),
+        });
+        entry.last_used = Instant::now();
+        Some(Arc::clone(&entry.cell))
+    }
+}
+
+/// Drops the least recently used half of the robots.txt not being fetched,
+/// they are fetched again if their origin comes back
+fn forget_least_recent(robots: &mut HashMap<String, RobotsEntry>) {
+    let mut unused = robots
+        .iter()
+        .filter(|(_, entry)| Arc::strong_count(&entry.cell) == 1)
+        .map(|(origin, entry)| (entry.last_used, origin.clone()))
+        .collect::<Vec<_>>();
+    unused.sort_unstable();
+    for (_, origin) in unused.into_iter().take(robots.len() / 2) {
+        robots.remove(&origin);
+    }
+}
+
+/// Parses `Retry-After`, either delay seconds or an http date
+fn retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.signed_duration_since(chrono::Utc::now());
+    Some(delay.to_std().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(host: &str) -> Url {
+        Url::parse(&format!("https://{host}/")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn idle_hosts_are_forgotten() {
+        let mut politeness = Politeness::new(&Config::default());
+        politeness.max_hosts = 2;
+
+        let busy = politeness.acquire(&url("busy.example")).await;
+        politeness.pause(&url("paused.example"), Duration::from_secs(60));
+        drop(politeness.acquire(&url("idle.example")).await);
+        assert_eq!(politeness.hosts.lock().unwrap().len(), 3);
+
+        // Only the idle host goes, the others still hold a permit or a pause
+        drop(politeness.acquire(&url("new.example")).await);
+        let mut hosts = politeness
+            .hosts
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        hosts.sort();
+        assert_eq!(hosts, ["busy.example", "new.example", "paused.example"]);
+        drop(busy);
+    }
+
+    #[test]
+    fn least_recent_robots_are_forgotten() {
+        let mut politeness = Politeness::new(&Config {
+            respect_robots: true,
+            ..Config::default()
+        });
+        politeness.max_robots = 4;
+
+        let fetching = politeness.robots_cell(&url("a.example")).unwrap();
+        for host in ["b.example", "c.example", "d.example", "a.example"] {
+            politeness.robots_cell(&url(host));
+        }
+        politeness.robots_cell(&url("e.example"));
+
+        // Half of the 4 kept are dropped, the oldest that are not being fetched
+        let mut origins = politeness
+            .robots
+            .lock()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        origins.sort();
+        assert_eq!(
+            origins,
+            [
+                "https://a.example",
+                "https://d.example",
+                "https://e.example"
+            ]
+        );
+        drop(fetching);
+    }
+
+    #[test]
+    fn retry_after_seconds() {
+        assert_eq!(retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(retry_after("-1"), None);
+        assert_eq!(retry_after("soon"), None);
+    }
+
+    #[test]
+    fn retry_after_http_date() {
+        let later = chrono::Utc::now() + chrono::Duration::seconds(90);
+        let delay = retry_after(&later.format("%a, %d %b %Y %H:%M:%S GMT").to_string()).unwrap();
+        assert!(delay > Duration::from_secs(80) && delay <= Duration::from_secs(90));
+
+        // Dates already passed are retried at once
+        assert_eq!(
+            retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+    }
+}
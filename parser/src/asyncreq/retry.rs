@@ -0,0 +1,71 @@
+use std::{fmt, time::Duration};
+
+use crate::{config::Config, parser::failure::FailureStage};
+
+/// Requests made for a url, attached to its response as an extension,
+/// or to the error as context once retries are exhausted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Attempts {
+    pub count: usize,
+    /// Kind of the last failed attempt, `None` when every attempt succeeded
+    pub last_error: Option<FailureStage>,
+}
+
+impl Attempts {
+    /// Adds the attempts of a later request, such as the next redirect hop
+    pub fn merge(&mut self, other: Attempts) {
+        self.count += other.count;
+        self.last_error = other.last_error.or(self.last_error);
+    }
+}
+
+impl fmt::Display for Attempts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "attempt {}", self.count)
+    }
+}
+
+/// How failed requests are retried
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub retries: usize,
+    /// Backoff before the first retry, doubled on every retry
+    pub base_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(config: &Config) -> Self {
+        RetryPolicy {
+            retries: config.retries,
+            base_backoff: config.retry_backoff,
+            max_backoff: config.max_retry_backoff,
+        }
+    }
+
+    /// Backoff before retrying a failed attempt, none when it should not be retried
+    pub fn backoff(&self, kind: FailureStage, attempt: usize) -> Option<Duration> {
+        if attempt >= self.retries || !is_retryable(kind) {
+            return None;
+        }
+
+        let delay = self
+            .base_backoff
+            .saturating_mul(2u32.saturating_pow(attempt as u32))
+            .min(self.max_backoff);
+        Some(jitter(delay))
+    }
+}
+
+/// Connection failures and requests interrupted before a response are worth another try.
+/// Timeouts are not, the timeout already bounds how long a url may take.
+fn is_retryable(kind: FailureStage) -> bool {
+    matches!(kind, FailureStage::Connect | FailureStage::Request)
+}
+
+/// Picks a delay between half and all of the backoff, so retries of urls
+/// that failed together do not hit the host together again
+fn jitter(delay: Duration) -> Duration {
+    delay.mul_f64(0.5 + fastrand::f64() / 2.0)
+}
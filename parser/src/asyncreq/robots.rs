@@ -0,0 +1,280 @@
+use std::{error::Error as StdError, fmt};
+
+use url::Url;
+
+/// Returned when robots.txt disallows fetching a url
+#[derive(Debug)]
+pub struct RobotsDisallowedError(pub String);
+
+impl fmt::Display for RobotsDisallowedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "disallowed by robots.txt: {}", self.0)
+    }
+}
+
+impl StdError for RobotsDisallowedError {}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// Rules of a robots.txt that apply to us, see RFC 9309
+#[derive(Debug, Clone, Default)]
+pub struct Robots {
+    rules: Vec<Rule>,
+}
+
+impl Robots {
+    /// Allows everything, used when robots.txt is missing
+    pub fn allow_all() -> Self {
+        Robots::default()
+    }
+
+    /// Disallows everything, used when robots.txt fails with a server error
+    pub fn disallow_all() -> Self {
+        Robots {
+            rules: vec![Rule {
+                allow: false,
+                pattern: "/".to_string(),
+            }],
+        }
+    }
+
+    /// Keeps the groups naming our product token, or the `*` groups when none do
+    pub fn parse(content: &str, token: &str) -> Self {
+        let mut ours = vec![];
+        let mut any = vec![];
+        // A group naming us applies even when it has no rules
+        let mut named_us = false;
+        // Whether the group being read names us or `*`, and whether its rules started
+        let mut group_is_ours = false;
+        let mut group_is_any = false;
+        let mut in_rules = false;
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        group_is_ours = false;
+                        group_is_any = false;
+                        in_rules = false;
+                    }
+                    if is_our_agent(value, token) {
+                        group_is_ours = true;
+                        named_us = true;
+                    }
+                    group_is_any |= value == "*";
+                }
+                key @ ("allow" | "disallow") => {
+                    in_rules = true;
+                    // An empty disallow allows everything
+                    if value.is_empty() {
+                        continue;
+                    }
+
+                    let rule = Rule {
+                        allow: key == "allow",
+                        pattern: value.to_string(),
+                    };
+                    if group_is_ours {
+                        ours.push(rule.clone());
+                    }
+                    if group_is_any {
+                        any.push(rule);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Robots {
+            rules: if named_us { ours } else { any },
+        }
+    }
+
+    /// The longest matching rule wins, allow wins ties
+    pub fn is_allowed(&self, url: &Url) -> bool {
+        let path = &url[url::Position::BeforePath..url::Position::AfterQuery];
+        let mut best: Option<&Rule> = None;
+
+        for rule in self
+            .rules
+            .iter()
+            .filter(|rule| matches(&rule.pattern, path))
+        {
+            best = match best {
+                Some(current)
+                    if current.pattern.len() > rule.pattern.len()
+                        || (current.pattern.len() == rule.pattern.len() && current.allow) =>
+                {
+                    Some(current)
+                }
+                _ => Some(rule),
+            };
+        }
+
+        !matches!(best, Some(Rule { allow: false, .. }))
+    }
+}
+
+/// Product token of a user agent, its name without version or comments
+pub fn product_token(user_agent: &str) -> &str {
+    user_agent
+        .trim()
+        .split(['/', ' '])
+        .next()
+        .unwrap_or_default()
+}
+
+/// Whether a user-agent line names our product token, ignoring case and any version
+fn is_our_agent(agent: &str, token: &str) -> bool {
+    product_token(agent).eq_ignore_ascii_case(token)
+}
+
+/// Matches a path against a pattern where `*` is any run of characters
+/// and a trailing `$` anchors the end
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts = parts.collect::<Vec<_>>();
+    for (i, part) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        if is_last && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    !anchored || rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN: &str = "sharkalyze";
+
+    fn allowed(robots: &Robots, path: &str) -> bool {
+        robots.is_allowed(&Url::parse(&format!("https://example.com{path}")).unwrap())
+    }
+
+    #[test]
+    fn our_group_wins_over_any() {
+        let robots = Robots::parse(
+            "User-agent: *\nDisallow: /\n\nUser-agent: Sharkalyze\nDisallow: /private\n",
+            TOKEN,
+        );
+        assert!(allowed(&robots, "/page"));
+        assert!(!allowed(&robots, "/private/page"));
+    }
+
+    #[test]
+    fn our_group_without_rules_allows_everything() {
+        let robots = Robots::parse(
+            "User-agent: sharkalyze\nDisallow:\n\nUser-agent: *\nDisallow: /\n",
+            TOKEN,
+        );
+        assert!(allowed(&robots, "/"));
+        assert!(allowed(&robots, "/page"));
+    }
+
+    #[test]
+    fn any_group_applies_when_we_are_not_named() {
+        let robots = Robots::parse(
+            "User-agent: otherbot\nDisallow: /\n\nUser-agent: *\nDisallow: /admin\n",
+            TOKEN,
+        );
+        assert!(allowed(&robots, "/page"));
+        assert!(!allowed(&robots, "/admin"));
+    }
+
+    #[test]
+    fn agent_must_be_our_whole_product_token() {
+        for agent in ["s", "shark", "sharkalyzer"] {
+            let robots = Robots::parse(
+                &format!("User-agent: {agent}\nDisallow: /\n\nUser-agent: *\nAllow: /\n"),
+                TOKEN,
+            );
+            assert!(allowed(&robots, "/page"), "{agent}");
+        }
+        let robots = Robots::parse("User-agent: SHARKALYZE/1.0\nDisallow: /\n", TOKEN);
+        assert!(!allowed(&robots, "/page"));
+    }
+
+    #[test]
+    fn groups_share_agents_and_merge() {
+        let robots = Robots::parse(
+            "User-agent: otherbot\nUser-agent: sharkalyze\nDisallow: /a\n\nUser-agent: sharkalyze\nDisallow: /b # comment\n", TOKEN);
+        assert!(!allowed(&robots, "/a"));
+        assert!(!allowed(&robots, "/b"));
+        assert!(allowed(&robots, "/c"));
+    }
+
+    #[test]
+    fn longest_match_wins_and_allow_wins_ties() {
+        let robots = Robots::parse(
+            "User-agent: *\nDisallow: /shop\nAllow: /shop/public\nAllow: /x\nDisallow: /x\n",
+            TOKEN,
+        );
+        assert!(!allowed(&robots, "/shop/cart"));
+        assert!(allowed(&robots, "/shop/public/item"));
+        assert!(allowed(&robots, "/x"));
+    }
+
+    #[test]
+    fn disallow_all_and_allow_all() {
+        assert!(!allowed(&Robots::disallow_all(), "/"));
+        assert!(allowed(&Robots::allow_all(), "/anything"));
+        assert!(allowed(&Robots::parse("", TOKEN), "/anything"));
+    }
+
+    #[test]
+    fn query_is_matched() {
+        let robots = Robots::parse("User-agent: *\nDisallow: /*?session=\n", TOKEN);
+        assert!(!allowed(&robots, "/page?session=1"));
+        assert!(allowed(&robots, "/page?id=1"));
+    }
+
+    #[test]
+    fn wildcard_and_anchor_matching() {
+        assert!(matches("/", "/anything"));
+        assert!(matches("/fish", "/fish.html"));
+        assert!(!matches("/fish", "/Fish"));
+        assert!(matches("/*.php", "/dir/index.php?x=1"));
+        assert!(matches("/*.php$", "/dir/index.php"));
+        assert!(!matches("/*.php$", "/dir/index.php?x=1"));
+        assert!(matches("/fish*", "/fish"));
+        assert!(matches("/a*b*c", "/axxbyyc/z"));
+        assert!(!matches("/a*b*c", "/axxc"));
+        assert!(matches("/page$", "/page"));
+        assert!(!matches("/page$", "/pages"));
+        assert!(matches("/*a*$", "/banana"));
+    }
+
+    #[test]
+    fn product_token_of_user_agent() {
+        assert_eq!(product_token("sharkalyze/0.1.0"), "sharkalyze");
+        assert_eq!(product_token("MyBot/2.0 (+https://example.com)"), "MyBot");
+        assert_eq!(product_token("plain"), "plain");
+    }
+}
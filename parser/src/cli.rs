@@ -0,0 +1,272 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+
+use sharkalyze_parser::{
+    config::Config,
+    output::{self, FlushPolicy, OutputFormat},
+    parser::brand::BrandList,
+};
+
+use crate::status::ProgressMode;
+
+/// Generates feature vectors for urls
+#[derive(Parser, Debug)]
+#[command(name = "sharkalyze-parser", version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Crawl urls and write their vectors to the output directory
+    Scan(ScanArgs),
+    /// Check inputs for malformed urls without fetching anything
+    Validate(ValidateArgs),
+    /// Compare two output files by url
+    Diff(DiffArgs),
+    /// Serve the analyzer over an http json api
+    Serve(ServeArgs),
+    /// Print the catalog of every feature as json
+    Schema,
+}
+
+#[derive(Args, Debug)]
+pub struct ScanArgs {
+    /// Urls, or paths to files with one url per line
+    #[arg(required = true, value_name = "URL_OR_PATH")]
+    pub inputs: Vec<String>,
+
+    /// Directory the results are written to
+    #[arg(short, long, value_name = "DIR", default_value = output::OUTPUT_DIR)]
+    pub output_dir: PathBuf,
+
+    /// Name shared by the files of this run, defaults to a timestamp
+    #[arg(short, long)]
+    pub name: Option<String>,
+
+    /// Skip urls completed by the run with this name (or the latest run)
+    /// and append to its files
+    #[arg(short, long)]
+    pub resume: bool,
+
+    /// Replace the files of an earlier run with the same name instead of refusing to
+    #[arg(long, conflicts_with = "resume")]
+    pub overwrite: bool,
+
+    /// Replay responses from these web archives (.warc or .warc.gz) instead of fetching
+    #[arg(long, value_name = "WARC")]
+    pub replay: Vec<PathBuf>,
+
+    /// Append every fetched response to this web archive (.warc or .warc.gz)
+    #[arg(long, value_name = "WARC", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    /// Format of the vectors and failures files
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Jsonl)]
+    pub format: OutputFormat,
+
+    /// Records written between flushes to disk
+    #[arg(long, value_name = "N", default_value_t = FlushPolicy::default().every)]
+    pub flush_every: usize,
+
+    /// Longest seconds a record may wait before being flushed to disk
+    #[arg(long, value_name = "SECS", default_value_t = FlushPolicy::default().interval.as_secs())]
+    pub flush_interval: u64,
+
+    #[command(flatten)]
+    pub limits: LimitArgs,
+
+    /// Do not print progress or the summary
+    #[arg(short, long)]
+    pub quiet: bool,
+
+    /// How progress is reported
+    #[arg(long, value_enum, default_value_t = ProgressMode::Bar)]
+    pub progress: ProgressMode,
+}
+
+impl ScanArgs {
+    pub fn flush_policy(&self) -> FlushPolicy {
+        FlushPolicy {
+            every: self.flush_every.max(1),
+            interval: std::time::Duration::from_secs(self.flush_interval),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct LimitArgs {
+    /// Urls analysed in parallel
+    #[arg(short, long, default_value_t = Config::default().concurrency)]
+    pub concurrency: usize,
+
+    /// Hyprlinks probed in parallel per page
+    #[arg(long, default_value_t = Config::default().link_concurrency)]
+    pub link_concurrency: usize,
+
+    /// Requests in flight across the whole scan
+    #[arg(long, default_value_t = Config::default().max_requests)]
+    pub max_requests: usize,
+
+    /// Seconds to wait for a page
+    #[arg(long, value_name = "SECS", default_value_t = Config::default().page_timeout.as_secs())]
+    pub timeout: u64,
+
+    /// Seconds to wait for each hyprlink probe
+    #[arg(long, value_name = "SECS", default_value_t = Config::default().link_timeout.as_secs())]
+    pub link_timeout: u64,
+
+    /// Do not fetch discovered hyprlinks
+    #[arg(long)]
+    pub no_link_probes: bool,
+
+    /// Redirects to follow before giving up on a url
+    #[arg(long, default_value_t = Config::default().max_redirects)]
+    pub max_redirects: usize,
+
+    /// Requests in flight to a single host
+    #[arg(long, default_value_t = Config::default().host_concurrency)]
+    pub host_concurrency: usize,
+
+    /// Requests started per second to a single host, 0 for unlimited
+    #[arg(long, value_name = "RPS", default_value_t = Config::default().host_rate_limit)]
+    pub host_rate_limit: f64,
+
+    /// User agent sent with every request, robots.txt is matched against its product token
+    #[arg(long, value_name = "UA", default_value_t = Config::default().user_agent)]
+    pub user_agent: String,
+
+    /// Skip urls disallowed by the host's robots.txt
+    #[arg(long)]
+    pub respect_robots: bool,
+
+    /// Analyse pages whose certificate fails verification by requesting them again,
+    /// accepting the certificate
+    #[arg(long)]
+    pub accept_invalid_certs: bool,
+
+    /// Retries of a request answered with 429 or 503
+    #[arg(long, default_value_t = Config::default().rate_limit_retries)]
+    pub rate_limit_retries: usize,
+
+    /// Longest Retry-After to wait for before giving up on a 429 or 503
+    #[arg(long, value_name = "SECS", default_value_t = Config::default().max_retry_after.as_secs())]
+    pub max_retry_after: u64,
+
+    /// Retries of a request that failed to connect or was cut off
+    #[arg(long, default_value_t = Config::default().retries)]
+    pub retries: usize,
+
+    /// Milliseconds to back off before the first retry, doubled on every retry
+    #[arg(long, value_name = "MS", default_value_t = Config::default().retry_backoff.as_millis() as u64)]
+    pub retry_backoff: u64,
+
+    /// Bytes of the page read before it is truncated
+    #[arg(long, value_name = "BYTES", default_value_t = Config::default().max_page_bytes)]
+    pub max_page_bytes: usize,
+
+    /// Bytes of each hyprlink read before it is truncated
+    #[arg(long, value_name = "BYTES", default_value_t = Config::default().max_link_bytes)]
+    pub max_link_bytes: usize,
+
+    /// Fetch hyprlinks to images, media and archives instead of sending HEAD
+    #[arg(long)]
+    pub no_head_probes: bool,
+
+    /// Follow same-site links this many clicks deep, scanning the pages reached
+    #[arg(long, value_name = "DEPTH", default_value_t = Config::default().crawl_depth)]
+    pub crawl_depth: usize,
+
+    /// Sub pages scanned at most per url when crawling
+    #[arg(long, value_name = "N", default_value_t = Config::default().crawl_pages)]
+    pub crawl_pages: usize,
+
+    /// Follow meta refreshes as redirects, scanning the page they lead to
+    #[arg(long)]
+    pub follow_meta_refresh: bool,
+
+    /// Brand list to check for impersonation, one `name... domain...` per line
+    #[arg(long, value_name = "FILE")]
+    pub brands: Option<PathBuf>,
+}
+
+impl LimitArgs {
+    pub fn to_config(&self) -> Result<Config> {
+        let brands = match &self.brands {
+            Some(path) => Arc::new(BrandList::from_file(path)?),
+            None => BrandList::embedded(),
+        };
+
+        Ok(Config {
+            concurrency: self.concurrency.max(1),
+            link_concurrency: self.link_concurrency.max(1),
+            max_requests: self.max_requests.max(1),
+            page_timeout: std::time::Duration::from_secs(self.timeout),
+            link_timeout: std::time::Duration::from_secs(self.link_timeout),
+            probe_hyprlinks: !self.no_link_probes,
+            max_redirects: self.max_redirects,
+            host_concurrency: self.host_concurrency.max(1),
+            host_rate_limit: self.host_rate_limit.max(0.0),
+            user_agent: self.user_agent.clone(),
+            respect_robots: self.respect_robots,
+            accept_invalid_certs: self.accept_invalid_certs,
+            rate_limit_retries: self.rate_limit_retries,
+            max_retry_after: std::time::Duration::from_secs(self.max_retry_after),
+            retries: self.retries,
+            retry_backoff: std::time::Duration::from_millis(self.retry_backoff),
+            max_retry_backoff: Config::default().max_retry_backoff,
+            max_page_bytes: self.max_page_bytes,
+            max_link_bytes: self.max_link_bytes,
+            head_probes: !self.no_head_probes,
+            crawl_depth: self.crawl_depth,
+            crawl_pages: self.crawl_pages,
+            follow_meta_refresh: self.follow_meta_refresh,
+            brands,
+        })
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct ValidateArgs {
+    /// Urls, or paths to files with one url per line
+    #[arg(required = true, value_name = "URL_OR_PATH")]
+    pub inputs: Vec<String>,
+
+    /// Print the report as json
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+    /// Output file to compare from
+    pub old: PathBuf,
+
+    /// Output file to compare to
+    pub new: PathBuf,
+
+    /// Print the report as json
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(short, long, value_name = "ADDR", default_value = "127.0.0.1:8081")]
+    pub bind: String,
+
+    #[command(flatten)]
+    pub limits: LimitArgs,
+
+    /// Jobs kept in memory before the oldest finished ones are forgotten
+    #[arg(long, default_value_t = 1000)]
+    pub max_jobs: usize,
+
+    /// Urls accepted per job
+    #[arg(long, default_value_t = 10000)]
+    pub max_batch: usize,
+}
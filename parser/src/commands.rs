@@ -0,0 +1,5 @@
+pub mod diff;
+pub mod scan;
+pub mod schema;
+pub mod serve;
+pub mod validate;
@@ -0,0 +1,130 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::cli::DiffArgs;
+
+#[derive(Serialize, Debug, Default)]
+struct DiffReport {
+    only_in_old: Vec<String>,
+    only_in_new: Vec<String>,
+    changed: BTreeMap<String, BTreeMap<String, FieldChange>>,
+}
+
+#[derive(Serialize, Debug)]
+struct FieldChange {
+    old: Value,
+    new: Value,
+}
+
+/// Compares two output files by url, field by field
+pub fn run(args: DiffArgs) -> Result<()> {
+    let old = read_vectors(&args.old)?;
+    let new = read_vectors(&args.new)?;
+    let mut report = DiffReport::default();
+
+    for (url, old_vector) in &old {
+        let Some(new_vector) = new.get(url) else {
+            report.only_in_old.push(url.clone());
+            continue;
+        };
+
+        let changes = diff_fields(old_vector, new_vector);
+        if !changes.is_empty() {
+            report.changed.insert(url.clone(), changes);
+        }
+    }
+    report.only_in_new = new
+        .keys()
+        .filter(|url| !old.contains_key(*url))
+        .cloned()
+        .collect();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    for url in &report.only_in_old {
+        println!("- {url}");
+    }
+    for url in &report.only_in_new {
+        println!("+ {url}");
+    }
+    for (url, changes) in &report.changed {
+        println!("~ {url}");
+        for (field, change) in changes {
+            println!("    {field}: {} -> {}", change.old, change.new);
+        }
+    }
+    println!(
+        "{} removed, {} added, {} changed.",
+        report.only_in_old.len(),
+        report.only_in_new.len(),
+        report.changed.len()
+    );
+
+    Ok(())
+}
+
+/// Reads a json or jsonl output file into vectors keyed by url
+fn read_vectors(path: &Path) -> Result<BTreeMap<String, Value>> {
+    let raw = fs::read_to_string(path)
+        .with_context(|| format!("failed to read output file: {}", path.display()))?;
+    let vectors: Vec<Value> = if raw.trim_start().starts_with('[') {
+        serde_json::from_str(&raw)
+            .with_context(|| format!("failed to parse output file: {}", path.display()))?
+    } else {
+        raw.lines()
+            .filter(|ln| !ln.trim().is_empty())
+            .map(serde_json::from_str)
+            .collect::<Result<_, _>>()
+            .with_context(|| format!("failed to parse output file: {}", path.display()))?
+    };
+
+    Ok(vectors
+        .into_iter()
+        .filter_map(|v| {
+            let url = v.get("url")?.as_str()?.to_string();
+            Some((url, v))
+        })
+        .collect())
+}
+
+/// Top level fields that differ, hyprlinks are compared by count only
+fn diff_fields(old: &Value, new: &Value) -> BTreeMap<String, FieldChange> {
+    let mut changes = BTreeMap::new();
+    let (Some(old), Some(new)) = (old.as_object(), new.as_object()) else {
+        return changes;
+    };
+
+    let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    for key in keys {
+        let old_value = comparable(old.get(key));
+        let new_value = comparable(new.get(key));
+        if old_value != new_value {
+            changes.insert(
+                key.clone(),
+                FieldChange {
+                    old: old_value,
+                    new: new_value,
+                },
+            );
+        }
+    }
+
+    changes
+}
+
+fn comparable(value: Option<&Value>) -> Value {
+    match value {
+        Some(Value::Array(items)) => Value::from(items.len()),
+        Some(v) => v.clone(),
+        None => Value::Null,
+    }
+}
@@ -0,0 +1,175 @@
+use anyhow::Result;
+use futures_util::StreamExt;
+use indicatif::{HumanDuration, ProgressBar, ProgressStyle};
+use sharkalyze_parser::{
+    output::{self, OutputFormat},
+    warc::{Archive, WarcWriter},
+    weburl, Analyzer, Failure,
+};
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use crate::{
+    cli::ScanArgs,
+    status::{self, ProgressEvent, ProgressMode},
+};
+
+pub async fn run(args: ScanArgs) -> Result<()> {
+    let show_bar = !args.quiet && args.progress == ProgressMode::Bar;
+    let show_json = !args.quiet && args.progress == ProgressMode::Json;
+
+    if args.resume && args.format != OutputFormat::Jsonl {
+        anyhow::bail!("only jsonl output can be resumed");
+    }
+
+    let mut urls = HashSet::new();
+    for input in &args.inputs {
+        urls.extend(weburl::get_urls(input)?);
+    }
+    if urls.is_empty() {
+        anyhow::bail!("no valid urls found");
+    }
+
+    // Ensure output directory exists
+    let output = match (&args.name, args.resume) {
+        (Some(name), resume) => {
+            output::ParserOutput::with_name(&args.output_dir, name.clone(), resume, args.overwrite)?
+        }
+        (None, true) => match output::ParserOutput::latest_run(&args.output_dir)? {
+            Some(name) => output::ParserOutput::with_name(&args.output_dir, name, true, false)?,
+            None => anyhow::bail!("no run to resume in {}", args.output_dir.display()),
+        },
+        (None, false) => output::ParserOutput::new(&args.output_dir)?,
+    };
+
+    // Skip what a previous run completed
+    let flush_policy = args.flush_policy();
+    let mut checkpoint = output.create_checkpoint(args.format, flush_policy)?;
+    let skipped_count = urls.len();
+    urls.retain(|url| !checkpoint.is_done(url.as_str()));
+    let skipped_count = skipped_count - urls.len();
+    if show_bar && args.resume {
+        println!(
+            "Resuming {}, skipping {} completed urls.",
+            output.name, skipped_count
+        );
+    }
+
+    // Progress bar
+    let start = Instant::now();
+    let total_count = urls.len();
+    let mut error_count = 0;
+    let mut ok_count = 0;
+
+    let progress_global_track = if show_bar {
+        ProgressBar::new(total_count as u64)
+    } else {
+        ProgressBar::hidden()
+    };
+    let spinner_style = ProgressStyle::default_bar()
+        .template(
+            "{spinner} {msg:25} [{wide_bar}] {percent}% ({pos}/{len}) {eta} {elapsed_precise}",
+        )?
+        .progress_chars("#>-")
+        .tick_strings(&["-", "\\", "|", "/"]);
+    progress_global_track.set_style(spinner_style);
+
+    // Global progress bar
+    if show_bar {
+        progress_global_track.enable_steady_tick(Duration::from_millis(500));
+    }
+    progress_global_track.set_message(status::format_progress_string(
+        ok_count,
+        error_count,
+        total_count,
+    ));
+
+    // Shares the connection pool across every url
+    let mut builder = Analyzer::builder().config(args.limits.to_config()?);
+    if !args.replay.is_empty() {
+        builder = builder.archive(Archive::open(&args.replay)?);
+    }
+    if let Some(path) = &args.record {
+        builder = builder.record(WarcWriter::create(path)?);
+    }
+    let analyzer = builder.build()?;
+
+    // Results are written as they complete
+    let mut vectors_writer = output.create_writer(args.format, flush_policy)?;
+    let mut failures_writer = output.create_failures_writer(args.format, flush_policy)?;
+
+    let mut results = analyzer.analyze_stream(urls.into_iter().map(|url| url.to_string()));
+    while let Some((url, result)) = results.next().await {
+        let ok = result.is_ok();
+        match result {
+            Ok(vector) => {
+                ok_count += 1;
+                vectors_writer.write(&vector)?;
+            }
+            Err(e) => {
+                error_count += 1;
+                failures_writer.write(&Failure::from_error(url.clone(), &e))?;
+            }
+        }
+
+        // Outputs first so the checkpoint never gets ahead of them
+        if checkpoint.record(&url, ok)? {
+            vectors_writer.flush()?;
+            failures_writer.flush()?;
+            checkpoint.flush()?;
+        }
+
+        progress_global_track.inc(1);
+        progress_global_track.set_message(status::format_progress_string(
+            ok_count,
+            error_count,
+            total_count,
+        ));
+        if show_json {
+            ProgressEvent::Progress {
+                url: &url,
+                ok,
+                succeeded: ok_count,
+                failed: error_count,
+                total: total_count,
+            }
+            .emit();
+        }
+    }
+
+    let out_filepath = vectors_writer.finish()?;
+    let failures_filepath = failures_writer.finish()?;
+    checkpoint.flush()?;
+    progress_global_track.finish_with_message("\x1b[32mDone!\x1b[0m");
+
+    if show_json {
+        ProgressEvent::Done {
+            succeeded: ok_count,
+            failed: error_count,
+            total: total_count,
+            elapsed_secs: start.elapsed().as_secs_f64(),
+            output: &out_filepath.to_string_lossy(),
+            failures: &failures_filepath.to_string_lossy(),
+        }
+        .emit();
+    }
+
+    if show_bar {
+        println!(
+            "{} urls done in {}.\n{} of {} failed to resolve.",
+            total_count,
+            HumanDuration(start.elapsed()),
+            error_count,
+            total_count
+        );
+
+        println!("Written to {}", out_filepath.display());
+        println!("Failures written to {}", failures_filepath.display());
+        if let Some(path) = &args.record {
+            println!("Responses archived to {}", path.display());
+        }
+    }
+
+    Ok(())
+}
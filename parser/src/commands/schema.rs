@@ -0,0 +1,8 @@
+use anyhow::Result;
+use sharkalyze_parser::schema;
+
+/// Prints the feature catalog for training and serving to check against
+pub fn run() -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(&schema::catalog())?);
+    Ok(())
+}
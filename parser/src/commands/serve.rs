@@ -0,0 +1,208 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sharkalyze_parser::{schema, weburl, Analyzer, Failure, Vector};
+
+use crate::cli::ServeArgs;
+
+const BASE_URL: &str = "/api/v1";
+
+struct ServeState {
+    analyzer: Analyzer,
+    jobs: Mutex<BTreeMap<u64, Job>>,
+    next_id: AtomicU64,
+    max_jobs: usize,
+    max_batch: usize,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum JobStatus {
+    Running,
+    Done,
+}
+
+#[derive(Serialize, Debug)]
+struct Job {
+    id: u64,
+    status: JobStatus,
+    total: usize,
+    completed: usize,
+    vectors: Vec<Vector>,
+    failures: Vec<Failure>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AnalyzeRequest {
+    url: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct BatchRequest {
+    urls: Vec<String>,
+}
+
+/// Serves the analyzer over http until interrupted
+pub async fn run(args: ServeArgs) -> Result<()> {
+    let analyzer = Analyzer::builder()
+        .config(args.limits.to_config()?)
+        .build()?;
+    let state = Arc::new(ServeState {
+        analyzer,
+        jobs: Mutex::new(BTreeMap::new()),
+        next_id: AtomicU64::new(1),
+        max_jobs: args.max_jobs.max(1),
+        max_batch: args.max_batch.max(1),
+    });
+
+    let app = Router::new()
+        .route(&format!("{BASE_URL}/healthcheck"), get(healthcheck))
+        .route(&format!("{BASE_URL}/schema"), get(schema))
+        .route(&format!("{BASE_URL}/analyze"), post(analyze))
+        .route(&format!("{BASE_URL}/jobs"), post(submit_job))
+        .route(&format!("{BASE_URL}/jobs/{{id}}"), get(job_status))
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind(&args.bind).await?;
+    println!("Listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+async fn healthcheck() -> Json<serde_json::Value> {
+    Json(json!({ "status": 200, "message": "ok" }))
+}
+
+async fn schema() -> Json<schema::Catalog> {
+    Json(schema::catalog())
+}
+
+/// Analyses a single url, responding once the vector is ready
+async fn analyze(
+    State(state): State<Arc<ServeState>>,
+    Json(body): Json<AnalyzeRequest>,
+) -> Response {
+    if let Err(e) = weburl::parse_url(&body.url) {
+        return error_response(StatusCode::BAD_REQUEST, e.to_string());
+    }
+
+    match state.analyzer.analyze(&body.url).await {
+        Ok(vector) => Json(vector).into_response(),
+        Err(e) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "failure": Failure::from_error(body.url, &e) })),
+        )
+            .into_response(),
+    }
+}
+
+/// Queues urls for analysis, responding with the job to poll
+async fn submit_job(
+    State(state): State<Arc<ServeState>>,
+    Json(body): Json<BatchRequest>,
+) -> Response {
+    if body.urls.is_empty() {
+        return error_response(StatusCode::BAD_REQUEST, "no urls given".to_string());
+    }
+    if body.urls.len() > state.max_batch {
+        return error_response(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("at most {} urls per job", state.max_batch),
+        );
+    }
+    if let Some(Err(e)) = body
+        .urls
+        .iter()
+        .map(|url| weburl::parse_url(url))
+        .find(Result::is_err)
+    {
+        return error_response(StatusCode::BAD_REQUEST, e.to_string());
+    }
+
+    let id = state.next_id.fetch_add(1, Ordering::Relaxed);
+    {
+        let mut jobs = state.jobs.lock().unwrap();
+        jobs.insert(
+            id,
+            Job {
+                id,
+                status: JobStatus::Running,
+                total: body.urls.len(),
+                completed: 0,
+                vectors: vec![],
+                failures: vec![],
+            },
+        );
+
+        // Forget the oldest finished jobs
+        while jobs.len() > state.max_jobs {
+            let Some(oldest) = jobs
+                .values()
+                .find(|job| job.status == JobStatus::Done)
+                .map(|job| job.id)
+            else {
+                break;
+            };
+            jobs.remove(&oldest);
+        }
+    }
+
+    tokio::spawn({
+        let state = Arc::clone(&state);
+        async move {
+            let mut results = state.analyzer.analyze_stream(body.urls);
+            while let Some((url, result)) = results.next().await {
+                let mut jobs = state.jobs.lock().unwrap();
+                let Some(job) = jobs.get_mut(&id) else {
+                    continue;
+                };
+
+                job.completed += 1;
+                match result {
+                    Ok(vector) => job.vectors.push(vector),
+                    Err(e) => job.failures.push(Failure::from_error(url, &e)),
+                }
+            }
+
+            if let Some(job) = state.jobs.lock().unwrap().get_mut(&id) {
+                job.status = JobStatus::Done;
+            }
+        }
+    });
+
+    (
+        StatusCode::ACCEPTED,
+        Json(json!({ "id": id, "status": JobStatus::Running })),
+    )
+        .into_response()
+}
+
+/// Reports progress and results of a job so far
+async fn job_status(State(state): State<Arc<ServeState>>, Path(id): Path<u64>) -> Response {
+    let jobs = state.jobs.lock().unwrap();
+    match jobs.get(&id) {
+        Some(job) => Json(job).into_response(),
+        None => error_response(StatusCode::NOT_FOUND, format!("no job with id {id}")),
+    }
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    (status, Json(json!({ "error": message }))).into_response()
+}
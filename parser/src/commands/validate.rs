@@ -0,0 +1,72 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use sharkalyze_parser::weburl;
+
+use crate::cli::ValidateArgs;
+
+#[derive(Serialize, Debug, Default)]
+struct ValidateReport {
+    valid: usize,
+    duplicate: usize,
+    invalid: Vec<InvalidLine>,
+}
+
+#[derive(Serialize, Debug)]
+struct InvalidLine {
+    input: String,
+    line: usize,
+    value: String,
+    reason: String,
+}
+
+/// Reports malformed and duplicate urls without fetching anything
+pub fn run(args: ValidateArgs) -> Result<()> {
+    let mut report = ValidateReport::default();
+    let mut seen = HashSet::new();
+
+    for input in &args.inputs {
+        for (i, (value, parsed)) in weburl::parse_input_lines(input)?.into_iter().enumerate() {
+            match parsed {
+                Ok(url) => {
+                    if seen.insert(url) {
+                        report.valid += 1;
+                    } else {
+                        report.duplicate += 1;
+                    }
+                }
+                Err(e) => report.invalid.push(InvalidLine {
+                    input: input.clone(),
+                    line: i + 1,
+                    value,
+                    reason: e.to_string(),
+                }),
+            }
+        }
+    }
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for invalid in &report.invalid {
+            println!(
+                "{}:{}: {} ({})",
+                invalid.input, invalid.line, invalid.reason, invalid.value
+            );
+        }
+        println!(
+            "{} valid, {} duplicate, {} invalid.",
+            report.valid,
+            report.duplicate,
+            report.invalid.len()
+        );
+    }
+
+    if report.valid == 0 {
+        anyhow::bail!("no valid urls found");
+    }
+
+    Ok(())
+}
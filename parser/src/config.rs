@@ -0,0 +1,90 @@
+use std::{sync::Arc, time::Duration};
+
+use crate::parser::brand::BrandList;
+
+/// User agent sent when none is configured
+pub const DEFAULT_USER_AGENT: &str = concat!("sharkalyze/", env!("CARGO_PKG_VERSION"));
+
+/// Tunables for a scan
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Urls analysed in parallel
+    pub concurrency: usize,
+    /// Hyprlinks probed in parallel per page
+    pub link_concurrency: usize,
+    /// Requests in flight across the whole scan
+    pub max_requests: usize,
+    /// Timeout for fetching the page itself
+    pub page_timeout: Duration,
+    /// Timeout for probing each hyprlink
+    pub link_timeout: Duration,
+    /// Whether discovered hyprlinks are fetched
+    pub probe_hyprlinks: bool,
+    /// Redirects followed before giving up on a url
+    pub max_redirects: usize,
+    /// Requests in flight to a single host
+    pub host_concurrency: usize,
+    /// Requests started per second to a single host, 0 for unlimited
+    pub host_rate_limit: f64,
+    /// User agent sent with every request, its product token is matched against robots.txt
+    pub user_agent: String,
+    /// Skip urls disallowed by the host's robots.txt
+    pub respect_robots: bool,
+    /// Send a request failing certificate verification again, accepting the certificate
+    pub accept_invalid_certs: bool,
+    /// Retries of a request answered with 429 or 503
+    pub rate_limit_retries: usize,
+    /// Longest `Retry-After` waited for before keeping the 429 or 503
+    pub max_retry_after: Duration,
+    /// Retries of a request that failed to connect or was cut off
+    pub retries: usize,
+    /// Backoff before the first retry, doubled on every retry and jittered
+    pub retry_backoff: Duration,
+    /// Longest backoff between retries
+    pub max_retry_backoff: Duration,
+    /// Bytes of the page read before it is truncated
+    pub max_page_bytes: usize,
+    /// Bytes of each hyprlink read before it is truncated
+    pub max_link_bytes: usize,
+    /// Probe hyprlinks to images, media and archives with HEAD, without reading their body
+    pub head_probes: bool,
+    /// Links followed from the page to reach same-site sub pages, 0 to scan only the page
+    pub crawl_depth: usize,
+    /// Sub pages scanned at most per page
+    pub crawl_pages: usize,
+    /// Follow meta refreshes as redirects, scanning the page they lead to
+    pub follow_meta_refresh: bool,
+    /// Brands pages are checked for impersonation against
+    pub brands: Arc<BrandList>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            concurrency: 50,
+            link_concurrency: 20,
+            max_requests: 500,
+            page_timeout: Duration::from_secs(300),
+            link_timeout: Duration::from_secs(30),
+            probe_hyprlinks: true,
+            max_redirects: 10,
+            host_concurrency: 6,
+            host_rate_limit: 0.0,
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            respect_robots: false,
+            accept_invalid_certs: false,
+            rate_limit_retries: 3,
+            max_retry_after: Duration::from_secs(60),
+            retries: 2,
+            retry_backoff: Duration::from_millis(500),
+            max_retry_backoff: Duration::from_secs(10),
+            max_page_bytes: 10 * 1024 * 1024,
+            max_link_bytes: 1024 * 1024,
+            head_probes: true,
+            crawl_depth: 0,
+            crawl_pages: 20,
+            follow_meta_refresh: false,
+            brands: BrandList::embedded(),
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Generates feature vectors for urls.
+//!
+//! ```no_run
+//! # async fn run() -> anyhow::Result<()> {
+//! let analyzer = sharkalyze_parser::Analyzer::builder()
+//!     .link_concurrency(10)
+//!     .build()?;
+//! let vector = analyzer.analyze("https://example.com").await?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod analyzer;
+pub mod asyncreq;
+pub mod config;
+pub mod output;
+pub mod parser;
+pub mod schema;
+pub mod warc;
+pub mod weburl;
+
+pub use analyzer::{Analyzer, AnalyzerBuilder};
+pub use config::Config;
+pub use parser::{
+    failure::{Failure, FailureStage},
+    vector::{Hyprlink, RedirectHop, SubPage, Vector},
+};
@@ -1,10 +1,172 @@
 use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
 
-use std::env;
+use crate::{parser::failure::Failure, schema};
+
+use std::collections::HashSet;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub mod checkpoint;
+pub mod csv;
+pub mod json;
+pub mod parquet;
+pub mod table;
+
+pub use checkpoint::Checkpoint;
+pub use json::{JsonArrayWriter, JsonlWriter};
+pub use table::{ColumnTypes, TableWriter};
 
-const OUTPUT_DIR: &str = "output";
+pub const OUTPUT_DIR: &str = "output";
+
+/// File formats results can be written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// One json object per line, written as each url completes
+    Jsonl,
+    /// A single pretty json array
+    Json,
+    /// Flat page table, hyprlinks in a separate table keyed by page url
+    Csv,
+    /// Same tables as csv in apache parquet, readable once the scan finishes
+    Parquet,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Jsonl => "jsonl",
+            OutputFormat::Json => "json",
+            OutputFormat::Csv => "csv",
+            OutputFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// When buffered records are flushed to disk
+#[derive(Clone, Copy, Debug)]
+pub struct FlushPolicy {
+    /// Records written between flushes
+    pub every: usize,
+    /// Longest time a record may sit in the buffer
+    pub interval: Duration,
+}
+
+impl Default for FlushPolicy {
+    fn default() -> Self {
+        FlushPolicy {
+            every: 100,
+            interval: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Buffered file that flushes according to a [`FlushPolicy`]
+pub(crate) struct FlushingFile {
+    pub(crate) writer: BufWriter<fs::File>,
+    pub(crate) filepath: PathBuf,
+    policy: FlushPolicy,
+    pending: usize,
+    last_flush: Instant,
+}
+
+impl FlushingFile {
+    pub(crate) fn new(output: Output, policy: FlushPolicy) -> Self {
+        FlushingFile {
+            writer: BufWriter::new(output.file),
+            filepath: output.filepath,
+            policy,
+            pending: 0,
+            last_flush: Instant::now(),
+        }
+    }
+
+    /// Counts a written record, flushing if due
+    pub(crate) fn record_written(&mut self) -> Result<()> {
+        if self.count_written() {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Counts a written record, returning whether a flush is due
+    pub(crate) fn count_written(&mut self) -> bool {
+        self.pending += 1;
+        self.pending >= self.policy.every || self.last_flush.elapsed() >= self.policy.interval
+    }
+
+    pub(crate) fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_data()?;
+        self.pending = 0;
+        self.last_flush = Instant::now();
+
+        Ok(())
+    }
+}
+
+/// Writes records in any [`OutputFormat`]
+pub enum RecordWriter {
+    Jsonl(JsonlWriter),
+    Json(JsonArrayWriter),
+    Table(Box<TableWriter>),
+}
+
+impl RecordWriter {
+    /// Column types are only used by the table formats
+    pub fn new(
+        output: Output,
+        format: OutputFormat,
+        policy: FlushPolicy,
+        columns: ColumnTypes,
+    ) -> Result<Self> {
+        Ok(match format {
+            OutputFormat::Jsonl => RecordWriter::Jsonl(JsonlWriter::new(output, policy)),
+            OutputFormat::Json => RecordWriter::Json(JsonArrayWriter::new(output, policy)?),
+            OutputFormat::Csv | OutputFormat::Parquet => {
+                RecordWriter::Table(Box::new(TableWriter::new(output, format, policy, columns)?))
+            }
+        })
+    }
+
+    pub fn filepath(&self) -> &PathBuf {
+        match self {
+            RecordWriter::Jsonl(w) => w.filepath(),
+            RecordWriter::Json(w) => w.filepath(),
+            RecordWriter::Table(w) => w.filepath(),
+        }
+    }
+
+    pub fn write<T: Serialize>(&mut self, record: &T) -> Result<()> {
+        match self {
+            RecordWriter::Jsonl(w) => w.write(record),
+            RecordWriter::Json(w) => w.write(record),
+            RecordWriter::Table(w) => w.write(record),
+        }
+    }
+
+    /// Flushes buffered records to disk
+    pub fn flush(&mut self) -> Result<()> {
+        match self {
+            RecordWriter::Jsonl(w) => w.flush(),
+            RecordWriter::Json(w) => w.flush(),
+            RecordWriter::Table(w) => w.flush(),
+        }
+    }
+
+    /// Flushes what is left and returns the written path
+    pub fn finish(self) -> Result<PathBuf> {
+        match self {
+            RecordWriter::Jsonl(w) => w.finish(),
+            RecordWriter::Json(w) => w.finish(),
+            RecordWriter::Table(w) => w.finish(),
+        }
+    }
+}
 
 pub struct Output {
     pub file: fs::File,
@@ -14,31 +176,217 @@ pub struct Output {
 #[derive(Clone, Debug)]
 pub struct ParserOutput {
     pub pathbuf: PathBuf,
+    /// Shared by every file of this run, a timestamp unless named
+    pub name: String,
+    /// Append to existing files instead of truncating them
+    pub resume: bool,
 }
 
+/// Returned when a run's files exist and it is neither resumed nor overwritten
+#[derive(Debug)]
+pub struct RunExistsError(pub PathBuf);
+
+impl std::fmt::Display for RunExistsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} already exists, use --resume to continue the run or --overwrite to replace it",
+            self.0.display()
+        )
+    }
+}
+
+impl std::error::Error for RunExistsError {}
+
 impl ParserOutput {
-    pub fn new() -> Result<Self> {
-        let pathbuf = ensure_output_dir()?;
-        Ok(ParserOutput { pathbuf })
+    /// Names the run after the current time, to the millisecond.
+    /// A run started in the same millisecond gets a numbered suffix instead of being refused.
+    pub fn new(output_dir: &Path) -> Result<Self> {
+        let timestamp = chrono::Utc::now().format("%Y-%m-%d_%H-%M-%S-%3f");
+        let mut output = ParserOutput {
+            pathbuf: ensure_output_dir(output_dir)?,
+            name: timestamp.to_string(),
+            resume: false,
+        };
+
+        let mut suffix = 1;
+        while output.existing_files().next().is_some() {
+            suffix += 1;
+            output.name = format!("{timestamp}-{suffix}");
+        }
+        Ok(output)
+    }
+
+    /// Uses a given name for every file of this run.
+    /// Files of an earlier run with the name are appended to when resuming,
+    /// truncated when overwriting, and refused otherwise.
+    pub fn with_name(
+        output_dir: &Path,
+        name: String,
+        resume: bool,
+        overwrite: bool,
+    ) -> Result<Self> {
+        let pathbuf = ensure_output_dir(output_dir)?;
+        let output = ParserOutput {
+            pathbuf,
+            name,
+            resume,
+        };
+
+        if !resume && !overwrite {
+            if let Some(existing) = output.existing_files().next() {
+                return Err(RunExistsError(existing).into());
+            }
+        }
+        Ok(output)
+    }
+
+    /// Vectors, failures and checkpoint files of this run already on disk
+    fn existing_files(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        OutputFormat::value_variants()
+            .iter()
+            .flat_map(|format| {
+                [
+                    format!("{}.{}", self.name, format.extension()),
+                    format!("{}.failures.{}", self.name, format.extension()),
+                ]
+            })
+            .chain([format!("{}{}", self.name, checkpoint::EXTENSION)])
+            .map(|filename| self.pathbuf.join(filename))
+            .filter(|filepath| filepath.exists())
+    }
+
+    /// Name of the run with the most recently written checkpoint
+    pub fn latest_run(output_dir: &Path) -> Result<Option<String>> {
+        let pathbuf = ensure_output_dir(output_dir)?;
+        let mut latest = None;
+
+        for entry in fs::read_dir(&pathbuf)? {
+            let entry = entry?;
+            let filename = entry.file_name().to_string_lossy().to_string();
+            let Some(name) = filename.strip_suffix(checkpoint::EXTENSION) else {
+                continue;
+            };
+
+            let modified = entry.metadata()?.modified()?;
+            let newer = match &latest {
+                Some((at, _)) => modified > *at,
+                None => true,
+            };
+            if newer {
+                latest = Some((modified, name.to_string()));
+            }
+        }
+
+        Ok(latest.map(|(_, name)| name))
     }
 
     /// Creates an output file and returns its path
     /// based on the timestamp
-    pub fn create_output(&self) -> Result<Output> {
-        let filename = format!("{}.json", chrono::Utc::now().format("%Y-%m-%d_%H-%M-%S"));
+    pub fn create_output(&self, format: OutputFormat) -> Result<Output> {
+        self.create_file(&format!("{}.{}", self.name, format.extension()))
+    }
+
+    /// Creates the failures file next to the output file
+    pub fn create_failures_output(&self, format: OutputFormat) -> Result<Output> {
+        self.create_file(&format!("{}.failures.{}", self.name, format.extension()))
+    }
+
+    /// Creates a writer for the vectors
+    pub fn create_writer(&self, format: OutputFormat, policy: FlushPolicy) -> Result<RecordWriter> {
+        RecordWriter::new(
+            self.create_output(format)?,
+            format,
+            policy,
+            schema::feature_type,
+        )
+    }
+
+    /// Creates a writer for the failures
+    pub fn create_failures_writer(
+        &self,
+        format: OutputFormat,
+        policy: FlushPolicy,
+    ) -> Result<RecordWriter> {
+        RecordWriter::new(
+            self.create_failures_output(format)?,
+            format,
+            policy,
+            Failure::column_type,
+        )
+    }
+
+    /// Opens the checkpoint of this run, loading it when resuming.
+    /// Jsonl records written after the last checkpoint flush count as done too.
+    pub fn create_checkpoint(
+        &self,
+        format: OutputFormat,
+        policy: FlushPolicy,
+    ) -> Result<Checkpoint> {
+        let filename = format!("{}{}", self.name, checkpoint::EXTENSION);
+        let mut done = HashSet::new();
+        if self.resume {
+            done = checkpoint::read_done(&self.pathbuf.join(&filename))?;
+
+            if format == OutputFormat::Jsonl {
+                for filename in [
+                    format!("{}.{}", self.name, format.extension()),
+                    format!("{}.failures.{}", self.name, format.extension()),
+                ] {
+                    done.extend(checkpoint::read_done(&self.pathbuf.join(filename))?);
+                }
+            }
+        }
+
+        Ok(Checkpoint::new(self.create_file(&filename)?, policy, done))
+    }
+
+    fn create_file(&self, filename: &str) -> Result<Output> {
         let filepath = self.pathbuf.join(filename);
-        let file = fs::File::create(&filepath)?;
+        let file = if self.resume {
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .read(true)
+                .append(true)
+                .open(&filepath)?;
+            truncate_partial_line(&mut file)?;
+            file
+        } else {
+            fs::File::create(&filepath)?
+        };
 
         Ok(Output { filepath, file })
     }
 }
 
+/// Drops a line left half written by an interrupted run
+fn truncate_partial_line(file: &mut fs::File) -> Result<()> {
+    let len = file.seek(SeekFrom::End(0))?;
+    let mut pos = len;
+    let mut byte = [0u8; 1];
+
+    while pos > 0 {
+        file.seek(SeekFrom::Start(pos - 1))?;
+        file.read_exact(&mut byte)?;
+        if byte[0] == b'\n' {
+            break;
+        }
+        pos -= 1;
+    }
+
+    if pos != len {
+        file.set_len(pos)?;
+    }
+
+    Ok(())
+}
+
 /// Ensure output directory
-pub fn ensure_output_dir() -> Result<PathBuf> {
-    let output_dir = env::current_dir()?.join(OUTPUT_DIR);
+pub fn ensure_output_dir(output_dir: &Path) -> Result<PathBuf> {
+    let output_dir = std::env::current_dir()?.join(output_dir);
 
     if !output_dir.exists() {
-        fs::create_dir(&output_dir)?;
+        fs::create_dir_all(&output_dir)?;
         return Ok(output_dir);
     }
 
@@ -52,3 +400,27 @@ pub fn ensure_output_dir() -> Result<PathBuf> {
 
     Ok(output_dir)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{OutputFormat, ParserOutput, RunExistsError};
+
+    #[test]
+    fn only_named_runs_are_refused() {
+        let output_dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+
+        let mut names = vec![];
+        for _ in 0..3 {
+            let output = ParserOutput::new(&output_dir).unwrap();
+            output.create_output(OutputFormat::Jsonl).unwrap();
+            names.push(output.name);
+        }
+        names.dedup();
+        assert_eq!(names.len(), 3);
+
+        let e = ParserOutput::with_name(&output_dir, names[0].clone(), false, false).unwrap_err();
+        assert!(e.is::<RunExistsError>());
+
+        std::fs::remove_dir_all(output_dir).unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::{FlushPolicy, FlushingFile, Output};
+
+pub const EXTENSION: &str = ".checkpoint";
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Entry {
+    url: String,
+    ok: bool,
+}
+
+/// Tracks urls that are done so an interrupted scan can be resumed.
+/// Only flush after the output files, so it never gets ahead of them.
+pub struct Checkpoint {
+    file: FlushingFile,
+    done: HashSet<String>,
+}
+
+impl Checkpoint {
+    pub fn new(output: Output, policy: FlushPolicy, done: HashSet<String>) -> Self {
+        Checkpoint {
+            file: FlushingFile::new(output, policy),
+            done,
+        }
+    }
+
+    pub fn filepath(&self) -> &PathBuf {
+        &self.file.filepath
+    }
+
+    /// Whether the url was completed by a previous run
+    pub fn is_done(&self, url: &str) -> bool {
+        self.done.contains(url)
+    }
+
+    pub fn done_count(&self) -> usize {
+        self.done.len()
+    }
+
+    /// Marks a url as done, returning whether a flush is due
+    pub fn record(&mut self, url: &str, ok: bool) -> Result<bool> {
+        serde_json::to_writer(
+            &mut self.file.writer,
+            &Entry {
+                url: url.to_string(),
+                ok,
+            },
+        )?;
+        self.file.writer.write_all(b"\n")?;
+        self.done.insert(url.to_string());
+
+        Ok(self.file.count_written())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Reads the `url` of every record in a checkpoint or jsonl file,
+/// skipping malformed lines
+pub fn read_done(path: &Path) -> Result<HashSet<String>> {
+    #[derive(Deserialize)]
+    struct WithUrl {
+        url: String,
+    }
+
+    let mut urls = HashSet::new();
+    if !path.exists() {
+        return Ok(urls);
+    }
+
+    let file = fs::File::open(path)
+        .with_context(|| format!("failed to open checkpoint: {}", path.display()))?;
+    for line in BufReader::new(file).lines() {
+        if let Ok(record) = serde_json::from_str::<WithUrl>(&line?) {
+            urls.insert(record.url);
+        }
+    }
+
+    Ok(urls)
+}
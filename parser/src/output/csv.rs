@@ -0,0 +1,84 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde_json::Value;
+
+use super::{table::Row, FlushPolicy, FlushingFile, Output};
+
+/// Comma separated table, the header comes from the first row
+pub struct CsvTable {
+    file: FlushingFile,
+    columns: Option<Vec<String>>,
+}
+
+impl CsvTable {
+    pub fn new(output: Output, policy: FlushPolicy) -> Self {
+        CsvTable {
+            file: FlushingFile::new(output, policy),
+            columns: None,
+        }
+    }
+
+    pub fn write_row(&mut self, row: &Row) -> Result<()> {
+        if self.columns.is_none() {
+            let columns: Vec<String> = row.iter().map(|(name, _)| name.clone()).collect();
+            write_line(&mut self.file, columns.iter().map(|c| escape(c)))?;
+            self.columns = Some(columns);
+        }
+        let Some(columns) = &self.columns else {
+            return Ok(());
+        };
+
+        // Later rows follow the header order, missing cells are left empty
+        let cells = columns.iter().map(|column| {
+            row.iter()
+                .find(|(name, _)| name == column)
+                .map(|(_, value)| cell(value))
+                .unwrap_or_default()
+        });
+        write_line(&mut self.file, cells)?;
+
+        self.file.record_written()
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.file.flush()
+    }
+
+    pub fn finish(mut self) -> Result<PathBuf> {
+        self.file.flush()?;
+        Ok(self.file.filepath)
+    }
+}
+
+fn write_line(file: &mut FlushingFile, cells: impl Iterator<Item = String>) -> Result<()> {
+    for (i, cell) in cells.enumerate() {
+        if i > 0 {
+            file.writer.write_all(b",")?;
+        }
+        file.writer.write_all(cell.as_bytes())?;
+    }
+    file.writer.write_all(b"\n")?;
+
+    Ok(())
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => escape(s),
+        other => escape(&other.to_string()),
+    }
+}
+
+/// Quotes a field if it contains separators, quotes or newlines
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,89 @@
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::{FlushPolicy, FlushingFile, Output};
+
+/// Appends one json object per line as records complete
+pub struct JsonlWriter {
+    file: FlushingFile,
+}
+
+impl JsonlWriter {
+    pub fn new(output: Output, policy: FlushPolicy) -> Self {
+        JsonlWriter {
+            file: FlushingFile::new(output, policy),
+        }
+    }
+
+    pub fn filepath(&self) -> &PathBuf {
+        &self.file.filepath
+    }
+
+    pub fn write<T: Serialize>(&mut self, record: &T) -> Result<()> {
+        serde_json::to_writer(&mut self.file.writer, record)?;
+        self.file.writer.write_all(b"\n")?;
+        self.file.record_written()
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.file.flush()
+    }
+
+    pub fn finish(mut self) -> Result<PathBuf> {
+        self.file.flush()?;
+        Ok(self.file.filepath)
+    }
+}
+
+/// Streams records into a pretty json array.
+/// The file is only valid json once finished.
+pub struct JsonArrayWriter {
+    file: FlushingFile,
+    empty: bool,
+}
+
+impl JsonArrayWriter {
+    pub fn new(output: Output, policy: FlushPolicy) -> Result<Self> {
+        let mut file = FlushingFile::new(output, policy);
+        file.writer.write_all(b"[")?;
+
+        Ok(JsonArrayWriter { file, empty: true })
+    }
+
+    pub fn filepath(&self) -> &PathBuf {
+        &self.file.filepath
+    }
+
+    pub fn write<T: Serialize>(&mut self, record: &T) -> Result<()> {
+        let pretty = serde_json::to_string_pretty(record)?;
+
+        self.file
+            .writer
+            .write_all(if self.empty { b"\n" } else { b",\n" })?;
+        for (i, line) in pretty.lines().enumerate() {
+            if i > 0 {
+                self.file.writer.write_all(b"\n")?;
+            }
+            self.file.writer.write_all(b"  ")?;
+            self.file.writer.write_all(line.as_bytes())?;
+        }
+
+        self.empty = false;
+        self.file.record_written()
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.file.flush()
+    }
+
+    pub fn finish(mut self) -> Result<PathBuf> {
+        self.file
+            .writer
+            .write_all(if self.empty { b"]" } else { b"\n]" })?;
+        self.file.flush()?;
+        Ok(self.file.filepath)
+    }
+}
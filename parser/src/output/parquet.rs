@@ -0,0 +1,260 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Result;
+use arrow_array::{
+    ArrayRef, BooleanArray, Float64Array, Int64Array, RecordBatch, StringArray, UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use serde_json::Value;
+
+use crate::schema::FeatureType;
+
+use super::{
+    table::{ColumnTypes, Row, PARENT_KEY},
+    Output,
+};
+
+/// Rows buffered before a row group is written
+const ROW_GROUP_SIZE: usize = 4096;
+
+/// Parquet table, typed from the declared column types.
+/// The file is only readable once finished.
+pub struct ParquetTable {
+    output: Option<Output>,
+    filepath: PathBuf,
+    writer: Option<ArrowWriter<std::fs::File>>,
+    schema: Option<SchemaRef>,
+    columns: ColumnTypes,
+    /// List field of the child table, `None` for the main table
+    list_field: Option<String>,
+    rows: Vec<Row>,
+}
+
+impl ParquetTable {
+    pub fn new(output: Output, columns: ColumnTypes, list_field: Option<String>) -> Self {
+        ParquetTable {
+            filepath: output.filepath.clone(),
+            output: Some(output),
+            writer: None,
+            schema: None,
+            columns,
+            list_field,
+            rows: vec![],
+        }
+    }
+
+    pub fn write_row(&mut self, row: &Row) -> Result<()> {
+        self.rows.push(row.clone());
+        if self.rows.len() >= ROW_GROUP_SIZE {
+            self.write_row_group()?;
+        }
+
+        Ok(())
+    }
+
+    /// Row groups are written once full, flushing early only fragments them
+    pub fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<PathBuf> {
+        self.write_row_group()?;
+
+        // Nothing written still needs a valid file
+        if self.writer.is_none() {
+            self.open_writer(Arc::new(Schema::empty()))?;
+        }
+        if let Some(writer) = self.writer.take() {
+            writer.close()?;
+        }
+
+        Ok(self.filepath)
+    }
+
+    fn write_row_group(&mut self) -> Result<()> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+
+        let schema = match &self.schema {
+            Some(schema) => Arc::clone(schema),
+            None => {
+                let schema = self.build_schema();
+                self.schema = Some(Arc::clone(&schema));
+                schema
+            }
+        };
+        if self.writer.is_none() {
+            self.open_writer(Arc::clone(&schema))?;
+        }
+
+        let columns = schema
+            .fields()
+            .iter()
+            .map(|field| build_column(field, &self.rows))
+            .collect();
+        let batch = RecordBatch::try_new(schema, columns)?;
+        if let Some(writer) = self.writer.as_mut() {
+            writer.write(&batch)?;
+            writer.flush()?;
+        }
+        self.rows.clear();
+
+        Ok(())
+    }
+
+    fn open_writer(&mut self, schema: SchemaRef) -> Result<()> {
+        if let Some(output) = self.output.take() {
+            self.writer = Some(ArrowWriter::try_new(output.file, schema, None)?);
+        }
+
+        Ok(())
+    }
+
+    /// Columns of the first row, typed as declared. Undeclared columns
+    /// follow their first non null value, so a null first row group cannot retype them.
+    fn build_schema(&self) -> SchemaRef {
+        let Some(first) = self.rows.first() else {
+            return Arc::new(Schema::empty());
+        };
+
+        let fields: Vec<Field> = first
+            .iter()
+            .map(|(name, _)| {
+                let declared = match self.list_field.as_deref() {
+                    Some(_) if name == PARENT_KEY => Some(FeatureType::String),
+                    list_field => (self.columns)(list_field, name),
+                };
+                let data_type = declared.map_or_else(
+                    || {
+                        self.rows
+                            .iter()
+                            .filter_map(|row| cell(row, name))
+                            .find(|value| !value.is_null())
+                            .map_or(DataType::Utf8, data_type)
+                    },
+                    declared_type,
+                );
+                Field::new(name, data_type, true)
+            })
+            .collect();
+
+        Arc::new(Schema::new(fields))
+    }
+}
+
+fn declared_type(kind: FeatureType) -> DataType {
+    match kind {
+        FeatureType::Flag | FeatureType::Count => DataType::UInt64,
+        FeatureType::Ratio | FeatureType::Float => DataType::Float64,
+        FeatureType::String | FeatureType::List => DataType::Utf8,
+    }
+}
+
+fn data_type(value: &Value) -> DataType {
+    match value {
+        Value::Bool(_) => DataType::Boolean,
+        Value::Number(n) if n.is_u64() => DataType::UInt64,
+        Value::Number(n) if n.is_i64() => DataType::Int64,
+        Value::Number(_) => DataType::Float64,
+        _ => DataType::Utf8,
+    }
+}
+
+fn cell<'a>(row: &'a Row, name: &str) -> Option<&'a Value> {
+    row.iter().find(|(n, _)| n == name).map(|(_, value)| value)
+}
+
+/// Builds a column, cells that do not fit its type become null
+fn build_column(field: &Field, rows: &[Row]) -> ArrayRef {
+    let cells = rows.iter().map(|row| cell(row, field.name()));
+
+    match field.data_type() {
+        DataType::Boolean => Arc::new(
+            cells
+                .map(|v| v.and_then(Value::as_bool))
+                .collect::<BooleanArray>(),
+        ),
+        DataType::UInt64 => Arc::new(
+            cells
+                .map(|v| v.and_then(Value::as_u64))
+                .collect::<UInt64Array>(),
+        ),
+        DataType::Int64 => Arc::new(
+            cells
+                .map(|v| v.and_then(Value::as_i64))
+                .collect::<Int64Array>(),
+        ),
+        DataType::Float64 => Arc::new(
+            cells
+                .map(|v| v.and_then(Value::as_f64))
+                .collect::<Float64Array>(),
+        ),
+        _ => Arc::new(
+            cells
+                .map(|v| match v {
+                    None | Some(Value::Null) => None,
+                    Some(Value::String(s)) => Some(s.clone()),
+                    Some(other) => Some(other.to_string()),
+                })
+                .collect::<StringArray>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{parser::failure::Failure, schema};
+
+    fn table(columns: ColumnTypes, list_field: Option<&str>) -> ParquetTable {
+        let filepath = std::env::temp_dir().join(format!("{}.parquet", uuid::Uuid::new_v4()));
+        let file = std::fs::File::create(&filepath).unwrap();
+        ParquetTable::new(
+            Output { file, filepath },
+            columns,
+            list_field.map(str::to_string),
+        )
+    }
+
+    fn column_type(schema: &Schema, name: &str) -> DataType {
+        schema.field_with_name(name).unwrap().data_type().clone()
+    }
+
+    #[test]
+    fn null_columns_keep_their_declared_type() {
+        let mut failures = table(Failure::column_type, None);
+        failures.rows.push(vec![
+            ("url".to_string(), json!("https://example.com/")),
+            ("status_code".to_string(), Value::Null),
+            ("message".to_string(), Value::Null),
+        ]);
+        let schema = failures.build_schema();
+
+        assert_eq!(column_type(&schema, "status_code"), DataType::UInt64);
+        assert_eq!(column_type(&schema, "message"), DataType::Utf8);
+        std::fs::remove_file(&failures.filepath).unwrap();
+    }
+
+    #[test]
+    fn columns_follow_the_catalog() {
+        let mut hyprlinks = table(schema::feature_type, Some("hyprlinks"));
+        hyprlinks.rows.push(vec![
+            ("page_url".to_string(), json!("https://example.com/")),
+            ("url_entropy".to_string(), json!(3)),
+            ("is_external".to_string(), json!(1)),
+            ("unknown".to_string(), json!(-1)),
+        ]);
+        let schema = hyprlinks.build_schema();
+
+        assert_eq!(column_type(&schema, "page_url"), DataType::Utf8);
+        assert_eq!(column_type(&schema, "url_entropy"), DataType::Float64);
+        assert_eq!(column_type(&schema, "is_external"), DataType::UInt64);
+        assert_eq!(column_type(&schema, "unknown"), DataType::Int64);
+        std::fs::remove_file(&hyprlinks.filepath).unwrap();
+    }
+}
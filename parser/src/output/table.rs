@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::schema::FeatureType;
+
+use super::{csv::CsvTable, parquet::ParquetTable, FlushPolicy, Output, OutputFormat};
+
+/// Column linking child table rows to their record
+pub const PARENT_KEY: &str = "page_url";
+
+/// Ordered cells of a flat row
+pub type Row = Vec<(String, Value)>;
+
+/// Declared type of a column, given the list field of its child table,
+/// `None` for the main table. Undeclared columns are typed from their values.
+pub type ColumnTypes = fn(Option<&str>, &str) -> Option<FeatureType>;
+
+/// A record split into a flat row and the rows of its child tables
+pub struct Flattened {
+    pub row: Row,
+    /// Arrays of objects, keyed by field name
+    pub children: Vec<(String, Vec<Row>)>,
+}
+
+/// Flattens a record in struct field order.
+/// Nested objects become `parent_child` columns, arrays of objects
+/// become child tables keyed by the record url and other arrays are json encoded.
+pub fn flatten<T: Serialize>(record: &T) -> Result<Flattened> {
+    // Through a string so floats keep their json formatting
+    let Value::Object(object) = serde_json::from_str(&serde_json::to_string(record)?)? else {
+        anyhow::bail!("only structs can be written as a table");
+    };
+
+    let parent_url = object.get("url").cloned().unwrap_or(Value::Null);
+    let mut flattened = Flattened {
+        row: vec![],
+        children: vec![],
+    };
+    flatten_object(
+        "",
+        object,
+        &mut flattened.row,
+        Some(&mut flattened.children),
+    );
+
+    for (_, rows) in flattened.children.iter_mut() {
+        for row in rows.iter_mut() {
+            row.insert(0, (PARENT_KEY.to_string(), parent_url.clone()));
+        }
+    }
+
+    Ok(flattened)
+}
+
+fn flatten_object(
+    prefix: &str,
+    object: Map<String, Value>,
+    row: &mut Row,
+    mut children: Option<&mut Vec<(String, Vec<Row>)>>,
+) {
+    for (key, value) in object {
+        let column = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}_{key}")
+        };
+
+        match value {
+            Value::Object(inner) => flatten_object(&column, inner, row, children.as_deref_mut()),
+            Value::Array(items) if items.iter().all(Value::is_object) && children.is_some() => {
+                let rows = items
+                    .into_iter()
+                    .map(|item| {
+                        let mut child_row = vec![];
+                        if let Value::Object(inner) = item {
+                            flatten_object("", inner, &mut child_row, None);
+                        }
+                        child_row
+                    })
+                    .collect();
+                if let Some(children) = children.as_deref_mut() {
+                    children.push((column, rows));
+                }
+            }
+            Value::Array(items) => {
+                row.push((column, Value::String(Value::Array(items).to_string())))
+            }
+            value => row.push((column, value)),
+        }
+    }
+}
+
+/// A single table file
+enum TableFile {
+    Csv(CsvTable),
+    Parquet(Box<ParquetTable>),
+}
+
+impl TableFile {
+    fn new(
+        output: Output,
+        format: OutputFormat,
+        policy: FlushPolicy,
+        columns: ColumnTypes,
+        list_field: Option<&str>,
+    ) -> Result<Self> {
+        Ok(match format {
+            OutputFormat::Parquet => TableFile::Parquet(Box::new(ParquetTable::new(
+                output,
+                columns,
+                list_field.map(str::to_string),
+            ))),
+            _ => TableFile::Csv(CsvTable::new(output, policy)),
+        })
+    }
+
+    fn write_row(&mut self, row: &Row) -> Result<()> {
+        match self {
+            TableFile::Csv(t) => t.write_row(row),
+            TableFile::Parquet(t) => t.write_row(row),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self {
+            TableFile::Csv(t) => t.flush(),
+            TableFile::Parquet(t) => t.flush(),
+        }
+    }
+
+    fn finish(self) -> Result<PathBuf> {
+        match self {
+            TableFile::Csv(t) => t.finish(),
+            TableFile::Parquet(t) => t.finish(),
+        }
+    }
+}
+
+/// Writes records as a flat table, with a child table per array of objects
+/// named `<file stem>.<field>.<extension>`
+pub struct TableWriter {
+    format: OutputFormat,
+    policy: FlushPolicy,
+    columns: ColumnTypes,
+    main_path: PathBuf,
+    main: TableFile,
+    children: BTreeMap<String, TableFile>,
+}
+
+impl TableWriter {
+    pub fn new(
+        output: Output,
+        format: OutputFormat,
+        policy: FlushPolicy,
+        columns: ColumnTypes,
+    ) -> Result<Self> {
+        Ok(TableWriter {
+            format,
+            policy,
+            columns,
+            main_path: output.filepath.clone(),
+            main: TableFile::new(output, format, policy, columns, None)?,
+            children: BTreeMap::new(),
+        })
+    }
+
+    pub fn filepath(&self) -> &PathBuf {
+        &self.main_path
+    }
+
+    pub fn write<T: Serialize>(&mut self, record: &T) -> Result<()> {
+        let flattened = flatten(record)?;
+        self.main.write_row(&flattened.row)?;
+
+        for (field, rows) in flattened.children {
+            if !self.children.contains_key(&field) {
+                let table = TableFile::new(
+                    self.child_output(&field)?,
+                    self.format,
+                    self.policy,
+                    self.columns,
+                    Some(&field),
+                )?;
+                self.children.insert(field.clone(), table);
+            }
+
+            if let Some(table) = self.children.get_mut(&field) {
+                for row in &rows {
+                    table.write_row(row)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.main.flush()?;
+        for table in self.children.values_mut() {
+            table.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<PathBuf> {
+        for table in self.children.into_values() {
+            table.finish()?;
+        }
+        self.main.finish()
+    }
+
+    fn child_output(&self, field: &str) -> Result<Output> {
+        let stem = self
+            .main_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let filepath = self
+            .main_path
+            .with_file_name(format!("{stem}.{field}.{}", self.format.extension()));
+        let file = std::fs::File::create(&filepath)?;
+
+        Ok(Output { file, filepath })
+    }
+}
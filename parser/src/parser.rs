@@ -1,22 +1,43 @@
-use std::{sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::Result;
 use futures_util::future::join_all;
+use reqwest::{header::CONTENT_TYPE, Method, StatusCode};
 use tokio::sync::Semaphore;
 use url::Url;
 
 use crate::{
-    asyncreq,
-    parser::vector::{format_bool, format_u8},
-    weburl,
+    asyncreq::{self, retry::Attempts, Requester},
+    config::Config,
+    parser::{
+        failure::FailureStage,
+        script::ScriptAnalysis,
+        site::Crawled,
+        vector::{format_bool, format_u8},
+    },
+    schema,
+    weburl::{self, LinkRelation},
 };
 
+pub mod brand;
+pub mod cloaking;
 pub mod crawl;
+pub mod failure;
+pub mod form;
+pub mod html;
 pub mod overlap;
+pub mod redirect;
+pub mod script;
+pub mod site;
+pub mod tls;
 pub mod vector;
 
 /// Entrypoint for parallel processing
-pub async fn generate_vector(client: reqwest::Client, url: String) -> Result<vector::Vector> {
+pub async fn generate_vector(
+    requester: Requester,
+    config: Arc<Config>,
+    url: String,
+) -> Result<vector::Vector> {
     let mut vector = vector::Vector::new(url.as_str().to_string());
 
     // SSL
@@ -26,121 +47,160 @@ pub async fn generate_vector(client: reqwest::Client, url: String) -> Result<vec
 
     // Calculating entropy
     vector.url_entropy = weburl::calculate_entropy(&vector.url);
+    vector.lexical = weburl::lexical::lexical_features(&vector.url);
 
     // Resolve url
-    let discovered_urls = crawl::crawl_page(&client, &mut vector).await?;
-    let root_url = Url::parse(url.as_str())?;
+    let mut discovered_urls = crawl::crawl_page(&requester, &config, &mut vector)
+        .await?
+        .links;
+    brand::apply(&config.brands, &mut vector);
+    // Relative hyprlinks are relative to where the page ended up
+    let root_url = Url::parse(&vector.final_url)?;
+    // Sub pages are not fetched again when probing the hyprlinks to them
+    let crawled = match config.crawl_depth {
+        0 => HashMap::new(),
+        _ => {
+            site::crawl(
+                &requester,
+                &config,
+                &mut vector,
+                &root_url,
+                &discovered_urls,
+            )
+            .await
+        }
+    };
+    site::apply(&mut vector);
+    if !config.probe_hyprlinks {
+        discovered_urls.clear();
+    }
 
     // Run concurrently
-    let semaphore = Arc::new(Semaphore::new(20));
+    let semaphore = Arc::new(Semaphore::new(config.link_concurrency));
     let mut futures = vec![];
 
     for to_fetch in &discovered_urls {
         let semaphore = Arc::clone(&semaphore);
-        let client = client.clone();
+        let requester = &requester;
+        let config = &config;
         let root_url = root_url.clone();
+        let crawled = &crawled;
 
         futures.push(async move {
             let _permit = semaphore.acquire().await?;
-            if let Ok(hyprlink_vector) =
-                generate_hyprlink_vector(&client, to_fetch, &root_url).await
-            {
-                if format_u8(hyprlink_vector.is_external) {
-                    vector.external_link_count += 1;
-                }
-                if format_u8(hyprlink_vector.is_samesite) {
-                    vector.samesite_link_count += 1;
-                }
-
-                // js
-                if format_u8(hyprlink_vector.is_javascript) {
-                    vector.javascript_count += 1;
-
-                    if format_u8(hyprlink_vector.is_external) {
-                        vector.external_javascript_count += 1;
-                    }
-                    if format_u8(hyprlink_vector.is_samesite) {
-                        vector.samesite_javascript_count += 1;
-                    }
-
-                    if format_u8(hyprlink_vector.is_successful_response) {
-                        vector.javascript_reachable_count += 1;
-                    } else {
-                        vector.javascript_unreachable_count += 1;
-                    }
-                }
-
-                return Ok(hyprlink_vector);
-            }
-
-            Err(anyhow::anyhow!("Failed to fetch {}", to_fetch))
+            generate_hyprlink_vector(requester, config, to_fetch, &root_url, crawled).await
         })
     }
 
     // resolve futures
     let binding = join_all(futures).await;
-    vector.hyprlinks.extend(binding.into_iter().flatten());
+    for (hyprlink_vector, script) in binding.into_iter().flatten() {
+        if let Some(script) = script {
+            script.apply(&mut vector);
+        }
 
-    vector.external_samesite_link_ratio =
-        vector.external_link_count as f32 / vector.samesite_link_count as f32;
-    vector.javascript_reachable_ratio =
-        vector.javascript_reachable_count as f32 / vector.javascript_unreachable_count as f32;
-    vector.external_samesite_javascript_ratio =
-        vector.external_javascript_count as f32 / vector.samesite_javascript_count as f32;
+        if format_u8(hyprlink_vector.is_external) {
+            vector.external_link_count += 1;
+        }
+        if format_u8(hyprlink_vector.is_samesite) {
+            vector.samesite_link_count += 1;
+        }
+        vector.same_origin_link_count += hyprlink_vector.is_same_origin as usize;
+        vector.same_site_link_count += hyprlink_vector.is_same_site as usize;
+        vector.third_party_link_count += hyprlink_vector.is_third_party as usize;
+
+        // js
+        if format_u8(hyprlink_vector.is_javascript) {
+            vector.javascript_count += 1;
+
+            if format_u8(hyprlink_vector.is_external) {
+                vector.external_javascript_count += 1;
+            }
+            if format_u8(hyprlink_vector.is_samesite) {
+                vector.samesite_javascript_count += 1;
+            }
+
+            if format_u8(hyprlink_vector.is_successful_response) {
+                vector.javascript_reachable_count += 1;
+            } else {
+                vector.javascript_unreachable_count += 1;
+            }
+        }
+
+        vector.hyprlinks.push(hyprlink_vector);
+    }
+
+    vector.resolve_ratios();
+    schema::validate(&vector)?;
 
     Ok(vector)
 }
 
-/// To generate a hyprlink vector
+/// To generate a hyprlink vector, and the analysis of its body when it is javascript
 async fn generate_hyprlink_vector(
-    client: &reqwest::Client,
+    requester: &Requester,
+    config: &Config,
     url_str: &str,
     root_url: &Url,
-) -> Result<vector::Hyprlink> {
+    crawled: &HashMap<String, Crawled>,
+) -> Result<(vector::Hyprlink, Option<ScriptAnalysis>)> {
     let mut hyprlink = vector::Hyprlink::new(url_str.to_string());
 
-    // Account for when url is relative
-    let url = if weburl::SAMESITE_URL_REGEXP.is_match(url_str) {
-        hyprlink.is_samesite = 1;
-
-        // handle root level /
-        let new_base = if url_str.starts_with('/') {
-            let mut new_to_fetch = root_url.clone();
-            new_to_fetch.set_path(url_str);
-
-            new_to_fetch
-        } else {
-            root_url.join(url_str)?
-        };
-
-        Url::parse(new_base.as_str())?.to_string()
-    } else {
-        url_str.to_string()
-    };
-
-    hyprlink.is_external = format_bool(url == root_url.to_string());
+    let resolved = resolve_link(root_url, url_str)?;
+    let relation = weburl::link_relation(&resolved, root_url);
+    hyprlink.is_same_origin = format_bool(relation == LinkRelation::SameOrigin);
+    hyprlink.is_same_site = format_bool(relation == LinkRelation::SameSite);
+    hyprlink.is_third_party = format_bool(relation == LinkRelation::ThirdParty);
+    hyprlink.is_samesite = format_bool(relation != LinkRelation::ThirdParty);
+    hyprlink.is_external = format_bool(relation == LinkRelation::ThirdParty);
+    let url = resolved.to_string();
 
     if url.starts_with("https://") {
         hyprlink.is_ssl_https = 1;
     }
 
     hyprlink.url_entropy = weburl::calculate_entropy(&url);
+    hyprlink.lexical = weburl::lexical::lexical_features(&url);
 
-    let req = match asyncreq::make_req(client.get(&url).timeout(Duration::from_secs(30))).await {
-        Ok(req) => req,
-        Err(e) => {
-            if e.is_timeout() {
-                hyprlink.request_timed_out = 1;
-                return Ok(hyprlink);
-            }
+    // Images, media and archives only need their headers
+    let head = config.head_probes && weburl::has_binary_extension(&resolved);
+    hyprlink.is_head_probe = format_bool(head);
+
+    if let Some(crawled) = crawled.get(&site::key(resolved.clone())) {
+        crawled.apply(&mut hyprlink, config.max_link_bytes);
+        return Ok((hyprlink, None));
+    }
 
-            anyhow::bail!(e);
+    let req = match probe(requester, config, &url, head).await {
+        Ok(fetched) => {
+            hyprlink.fetch_attempts = fetched.attempts.count;
+            hyprlink.fetch_error = fetched
+                .attempts
+                .last_error
+                .map(|kind| kind.as_str().to_string())
+                .unwrap_or_default();
+            hyprlink.certificate_invalid = format_bool(
+                fetched
+                    .response
+                    .extensions()
+                    .get::<asyncreq::CertificateInvalid>()
+                    .is_some(),
+            );
+            fetched.response
+        }
+        // Kept so unreachable hyprlinks still count, with why they failed
+        Err(e) => {
+            let kind = FailureStage::of(&e);
+            hyprlink.request_timed_out = format_bool(kind == FailureStage::Timeout);
+            hyprlink.fetch_attempts = e.downcast_ref::<Attempts>().map_or(0, |a| a.count);
+            hyprlink.fetch_error = kind.as_str().to_string();
+            return Ok((hyprlink, None));
         }
     };
 
     if !req.status().is_success() {
         hyprlink.is_successful_response = 0;
-        return Ok(hyprlink);
+        return Ok((hyprlink, None));
     }
     hyprlink.is_successful_response = 1;
 
@@ -178,10 +238,79 @@ async fn generate_hyprlink_vector(
         }
     }
 
-    hyprlink.content_length = usize::max(
-        req.content_length().unwrap_or(0) as usize,
-        req.text().await.unwrap_or(String::new()).len(),
-    );
+    let declared_length = crawl::declared_length(&req);
+    let is_script = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|val| val.to_str().ok())
+        .is_some_and(|header| header.contains("javascript") || header.contains("ecmascript"));
+
+    let mut script = None;
+    let read_length = match head {
+        true => 0,
+        false => match asyncreq::read_body(req, config.max_link_bytes).await {
+            Ok((body, truncated)) => {
+                hyprlink.body_truncated = format_bool(truncated);
+                if is_script {
+                    script = Some(ScriptAnalysis::analyze(&String::from_utf8_lossy(&body)));
+                }
+                body.len()
+            }
+            Err(_) => 0,
+        },
+    };
+    hyprlink.content_length = usize::max(declared_length, read_length);
+
+    Ok((hyprlink, script))
+}
+
+/// Resolves a relative hyprlink against the page.
+/// Root level / only replaces the page's path, keeping the rest of the page url.
+fn resolve_link(root_url: &Url, url_str: &str) -> Result<Url> {
+    // Protocol relative links name another host, they are not root level
+    if url_str.starts_with('/') && !url_str.starts_with("//") {
+        let mut resolved = root_url.clone();
+        resolved.set_path(url_str);
+        return Ok(resolved);
+    }
+
+    Ok(root_url.join(url_str)?)
+}
+
+/// Fetches a hyprlink, or only its headers.
+/// Servers that refuse HEAD are sent a get, whose body is then left unread.
+async fn probe(
+    requester: &Requester,
+    config: &Config,
+    url: &str,
+    head: bool,
+) -> Result<redirect::Fetched> {
+    let fetch = |method| {
+        redirect::fetch(
+            requester,
+            method,
+            url,
+            config.link_timeout,
+            config.max_link_bytes,
+            config.max_redirects,
+        )
+    };
+
+    if !head {
+        return fetch(Method::GET).await;
+    }
+
+    let fetched = fetch(Method::HEAD).await?;
+    if !matches!(
+        fetched.response.status(),
+        StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+    ) {
+        return Ok(fetched);
+    }
 
-    Ok(hyprlink)
+    let mut attempts = fetched.attempts;
+    let mut fetched = fetch(Method::GET).await?;
+    attempts.merge(fetched.attempts);
+    fetched.attempts = attempts;
+    Ok(fetched)
 }
@@ -0,0 +1,302 @@
+use std::{fs, path::Path, sync::Arc};
+
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use unicode_security::skeleton;
+use url::{Host, Url};
+
+use crate::weburl;
+
+use super::{
+    html::PageScan,
+    vector::{format_bool, BrandMention, Vector},
+};
+
+/// Shortest domain label checked for typosquatting, shorter ones are a few edits
+/// away from too many unrelated domains
+const MIN_TYPOSQUAT_LABEL: usize = 5;
+/// Edits allowed per this many characters of the brand's label
+const TYPOSQUAT_CHARS_PER_EDIT: usize = 4;
+
+lazy_static! {
+    static ref DEFAULT_BRANDS: Arc<BrandList> =
+        Arc::new(BrandList::parse(include_str!("../../data/brands.txt")).unwrap());
+}
+
+/// A brand and the domains it owns
+#[derive(Debug, Clone)]
+pub struct Brand {
+    pub name: String,
+    pub domains: Vec<String>,
+}
+
+impl Brand {
+    /// Times the name appears among words, as whole words.
+    /// Names of several words also match written as one, such as `americanexpress`.
+    fn mentions(&self, words: &[String]) -> usize {
+        let name = self.name.split_whitespace().collect::<Vec<_>>();
+        if name.is_empty() {
+            return 0;
+        }
+
+        let sequences = words
+            .windows(name.len())
+            .filter(|window| window.iter().zip(&name).all(|(word, part)| word == part))
+            .count();
+        let joined = match name.len() {
+            1 => 0,
+            _ => {
+                let joined = name.concat();
+                words.iter().filter(|word| **word == joined).count()
+            }
+        };
+        sequences + joined
+    }
+}
+
+/// Brands pages are compared against.
+/// Each line is a brand name, of one or more words, followed by its domains,
+/// `#` starts a comment.
+#[derive(Debug, Clone)]
+pub struct BrandList {
+    pub brands: Vec<Brand>,
+}
+
+impl BrandList {
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut brands = vec![];
+        for (i, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            // Domains start at the first word with a dot, the name is the words before it
+            let mut words = line
+                .split_whitespace()
+                .map(str::to_lowercase)
+                .collect::<Vec<_>>();
+            let first_domain = words
+                .iter()
+                .position(|word| word.contains('.'))
+                .unwrap_or(words.len());
+            let domains = words.split_off(first_domain);
+            let name = words.join(" ");
+            if name.is_empty() {
+                anyhow::bail!("line {}: brand has no name", i + 1);
+            }
+            if domains.is_empty() {
+                anyhow::bail!("line {}: brand {name} has no domains", i + 1);
+            }
+            brands.push(Brand { name, domains });
+        }
+
+        Ok(BrandList { brands })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("failed to read brand list: {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("invalid brand list: {}", path.display()))
+    }
+
+    /// Shared list embedded from `data/brands.txt`
+    pub fn embedded() -> Arc<Self> {
+        Arc::clone(&DEFAULT_BRANDS)
+    }
+}
+
+/// Sets the brand features of the page from where it ended up
+pub fn apply(brands: &BrandList, vector: &mut Vector) {
+    let Some(url) = page_url(vector) else {
+        return;
+    };
+    // Brands own domains, ip addresses cannot impersonate them
+    let Some(Host::Domain(host)) = url.host() else {
+        vector.host_unicode = url.host_str().unwrap_or_default().to_string();
+        return;
+    };
+
+    let (host_unicode, _) = idna::domain_to_unicode(host);
+    let domain = weburl::registrable_domain(&url).unwrap_or_else(|| host.to_string());
+    let (domain_unicode, _) = idna::domain_to_unicode(&domain);
+    let domain_skeleton = skeleton(&domain_unicode).collect::<String>();
+    let subdomain = host_unicode
+        .strip_suffix(domain_unicode.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let label = domain_unicode.split('.').next().unwrap_or_default();
+    let domain_words = words(&domain_unicode);
+    let subdomain_words = words(&subdomain);
+    let rest_words = words(&format!(
+        "{} {}",
+        url.path(),
+        url.query().unwrap_or_default()
+    ));
+    let is_official = brands
+        .brands
+        .iter()
+        .any(|brand| brand.domains.contains(&domain));
+
+    let mut nearest: Option<(usize, &str)> = None;
+    for brand in &brands.brands {
+        for brand_domain in &brand.domains {
+            let distance = strsim::levenshtein(&domain_unicode, brand_domain);
+            if !matches!(nearest, Some((best, _)) if best <= distance) {
+                nearest = Some((distance, &brand.name));
+            }
+
+            if domain != *brand_domain
+                && domain_skeleton == skeleton(brand_domain).collect::<String>()
+            {
+                vector.confusable_brand_match = 1;
+            }
+            if !is_official && is_typosquat(label, brand_domain) {
+                vector.typosquat_brand = 1;
+            }
+        }
+
+        if !is_official {
+            vector.brand_in_domain |= format_bool(brand.mentions(&domain_words) > 0);
+            vector.brand_in_subdomain |= format_bool(brand.mentions(&subdomain_words) > 0);
+            vector.brand_in_path |= format_bool(brand.mentions(&rest_words) > 0);
+        }
+    }
+
+    vector.host_unicode = host_unicode;
+    vector.is_brand_domain = format_bool(is_official);
+    if let Some((distance, name)) = nearest {
+        vector.brand_min_edit_distance = distance;
+        vector.nearest_brand = name.to_string();
+    }
+}
+
+/// Whether the label of a domain is a few edits away from the label of a brand domain,
+/// or is the same label under another suffix. Allowed edits grow with the label length.
+fn is_typosquat(label: &str, brand_domain: &str) -> bool {
+    let brand_label = brand_domain.split('.').next().unwrap_or_default();
+    let length = brand_label.chars().count();
+    if length < MIN_TYPOSQUAT_LABEL {
+        return false;
+    }
+
+    strsim::levenshtein(label, brand_label) <= length / TYPOSQUAT_CHARS_PER_EDIT
+}
+
+/// Counts brand names in the text of the page, matched as whole words
+pub fn apply_content(brands: &BrandList, vector: &mut Vector, scan: &PageScan) {
+    let domain = page_url(vector).and_then(|url| weburl::registrable_domain(&url));
+    let sources = [
+        scan.title.as_deref().unwrap_or_default(),
+        &scan.meta_text,
+        &scan.image_alt_text,
+        &scan.label_text,
+    ]
+    .map(words);
+
+    for brand in &brands.brands {
+        let [title_count, meta_count, image_alt_count, label_count] =
+            sources.each_ref().map(|words| brand.mentions(words));
+        let count = title_count + meta_count + image_alt_count + label_count;
+        if count == 0 {
+            continue;
+        }
+
+        let owns_domain = domain.as_ref().is_some_and(|d| brand.domains.contains(d));
+        vector.brand_mention_count += count;
+        vector.brand_domain_mismatch |= format_bool(!owns_domain);
+        vector.brand_mentions.push(BrandMention {
+            brand: brand.name.clone(),
+            title_count,
+            meta_count,
+            image_alt_count,
+            label_count,
+            owns_domain: format_bool(owns_domain),
+        });
+    }
+    vector.mentioned_brand_count = vector.brand_mentions.len();
+}
+
+/// Where the page ended up, or the submitted url before it is fetched
+fn page_url(vector: &Vector) -> Option<Url> {
+    let url = if vector.final_url.is_empty() {
+        &vector.url
+    } else {
+        &vector.final_url
+    };
+    Url::parse(url).ok()
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applied(url: &str) -> Vector {
+        let mut vector = Vector::new(url.to_string());
+        apply(&BrandList::embedded(), &mut vector);
+        vector
+    }
+
+    #[test]
+    fn typosquats_scale_with_label_length() {
+        assert_eq!(applied("https://paypa1.com/").typosquat_brand, 1);
+        assert_eq!(applied("https://paypal.co/").typosquat_brand, 1);
+        assert_eq!(applied("https://rnastercard.com/").typosquat_brand, 1);
+        // Short brands are a couple of edits away from too much
+        assert_eq!(applied("https://bbc.com/").typosquat_brand, 0);
+        assert_eq!(applied("https://dbc.com/").typosquat_brand, 0);
+        assert_eq!(applied("https://www.paypal.com/").typosquat_brand, 0);
+    }
+
+    #[test]
+    fn names_of_several_words() {
+        let list = BrandList::parse("Bank of  America bankofamerica.com bofa.com").unwrap();
+        let brand = &list.brands[0];
+        assert_eq!(brand.name, "bank of america");
+        assert_eq!(brand.domains, ["bankofamerica.com", "bofa.com"]);
+
+        let text = words("Sign in to Bank of America, bankofamerica and bank of americas");
+        assert_eq!(brand.mentions(&text), 2);
+        assert!(BrandList::parse("bank of america").is_err());
+        assert!(BrandList::parse("bankofamerica.com").is_err());
+    }
+
+    #[test]
+    fn several_word_names_in_content() {
+        let mut vector = Vector::new("https://amex-rewards.test/".to_string());
+        let scan = PageScan {
+            title: Some("American Express | Sign in".to_string()),
+            label_text: "Your American Express card number".to_string(),
+            ..Default::default()
+        };
+        apply_content(&BrandList::embedded(), &mut vector, &scan);
+
+        assert_eq!(vector.brand_mention_count, 2);
+        assert_eq!(vector.brand_mentions[0].brand, "american express");
+        assert_eq!(vector.brand_domain_mismatch, 1);
+    }
+
+    #[test]
+    fn brand_names_match_whole_words() {
+        let vector = applied("https://fuobar.com/dhlx?q=paypalish");
+        assert_eq!(vector.brand_in_domain, 0);
+        assert_eq!(vector.brand_in_path, 0);
+
+        let vector = applied("https://secure.uob-login.com/dhl/track?from=paypal");
+        assert_eq!(vector.brand_in_domain, 1);
+        assert_eq!(vector.brand_in_path, 1);
+        assert_eq!(vector.brand_in_subdomain, 0);
+
+        let vector = applied("https://paypal.account-check.com/");
+        assert_eq!(vector.brand_in_subdomain, 1);
+        assert_eq!(vector.brand_in_domain, 0);
+    }
+}
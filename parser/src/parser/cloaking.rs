@@ -0,0 +1,157 @@
+use url::Url;
+
+use crate::weburl;
+
+use super::{
+    html::PageScan,
+    script,
+    vector::{format_bool, format_u8, Vector},
+};
+
+/// Delay and target of a `<meta http-equiv="refresh">` content,
+/// such as `5; url=https://example.com`. The target is `None` when it reloads the page.
+pub fn meta_refresh(content: &str) -> Option<(usize, Option<&str>)> {
+    let (delay, target) = match content.find([';', ',']) {
+        Some(index) => (&content[..index], Some(&content[index + 1..])),
+        None => (content, None),
+    };
+
+    // Fractions are allowed, only whole seconds are kept
+    let delay = delay.trim().split('.').next()?.parse::<usize>().ok()?;
+    let target = target.map(str::trim).map(|target| {
+        let target = match target.get(..3) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("url") => target[3..]
+                .trim_start()
+                .trim_start_matches('=')
+                .trim_start(),
+            _ => target,
+        };
+        target.trim_matches(|c| c == '\'' || c == '"').trim()
+    });
+
+    Some((delay, target.filter(|target| !target.is_empty())))
+}
+
+/// Target of the page's meta refresh, `None` when there is none or it reloads the page
+pub fn meta_refresh_target(scan: &PageScan, page_url: &Url) -> Option<Url> {
+    let (_, target) = meta_refresh(scan.meta_refresh.as_deref()?)?;
+    page_url
+        .join(target?)
+        .ok()
+        .filter(|target| target != page_url)
+}
+
+/// Sets the meta refresh features of the page, unless a page refreshing to it already set them
+pub fn apply_meta_refresh(vector: &mut Vector, scan: &PageScan, page_url: &Url) {
+    if format_u8(vector.has_meta_refresh) {
+        return;
+    }
+
+    if let Some((delay, target)) = scan.meta_refresh.as_deref().and_then(meta_refresh) {
+        vector.has_meta_refresh = 1;
+        vector.meta_refresh_delay = delay;
+        vector.meta_refresh_external =
+            format_bool(target.is_some_and(|target| is_external(page_url, target)));
+    }
+}
+
+/// Sets the script redirect and frame features of the page
+pub fn apply(vector: &mut Vector, scan: &PageScan, page_url: &Url) {
+    for target in scan
+        .inline_scripts
+        .iter()
+        .flat_map(|script| script::location_targets(script))
+    {
+        vector.js_redirect_count += 1;
+        if target
+            .as_deref()
+            .is_some_and(|target| is_external(page_url, target))
+        {
+            vector.js_redirect_external = 1;
+        }
+    }
+
+    vector.iframe_count = scan.iframe_count;
+    vector.hidden_iframe_count = scan.hidden_iframe_count;
+    vector.external_iframe_count = scan
+        .iframe_sources
+        .iter()
+        .filter(|src| is_external(page_url, src))
+        .count();
+}
+
+/// Whether a target, relative to the page, is on another registrable domain
+fn is_external(page_url: &Url, target: &str) -> bool {
+    page_url
+        .join(target)
+        .is_ok_and(|url| weburl::registrable_domain(&url) != weburl::registrable_domain(page_url))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::html::HtmlScanner;
+
+    #[test]
+    fn meta_refresh_url_forms() {
+        let target = Some("https://example.com/");
+        assert_eq!(
+            meta_refresh("0; url=https://example.com/"),
+            Some((0, target))
+        );
+        assert_eq!(
+            meta_refresh("0;URL='https://example.com/'"),
+            Some((0, target))
+        );
+        assert_eq!(
+            meta_refresh("5 ; Url = \"https://example.com/\""),
+            Some((5, target))
+        );
+        assert_eq!(meta_refresh("0, https://example.com/"), Some((0, target)));
+        assert_eq!(
+            meta_refresh("2.5;url=https://example.com/"),
+            Some((2, target))
+        );
+        // Only the first separator splits, urls may contain them
+        assert_eq!(meta_refresh("0;url=/a;b"), Some((0, Some("/a;b"))));
+    }
+
+    #[test]
+    fn meta_refresh_without_target() {
+        assert_eq!(meta_refresh("30"), Some((30, None)));
+        assert_eq!(meta_refresh("30;"), Some((30, None)));
+        assert_eq!(meta_refresh("0; url="), Some((0, None)));
+        assert_eq!(meta_refresh("soon; url=/next"), None);
+        assert_eq!(meta_refresh(""), None);
+    }
+
+    #[test]
+    fn meta_refresh_target_resolves() {
+        let page = Url::parse("https://example.com/a/page").unwrap();
+        let scan = |content: &str| PageScan {
+            meta_refresh: Some(content.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            meta_refresh_target(&scan("0; url=next"), &page).map(String::from),
+            Some("https://example.com/a/next".to_string())
+        );
+        assert_eq!(meta_refresh_target(&scan("0; url=page"), &page), None);
+        assert_eq!(meta_refresh_target(&scan("10"), &page), None);
+    }
+
+    #[test]
+    fn meta_refresh_in_noscript() {
+        let mut scanner = HtmlScanner::new(Url::parse("https://example.com/").unwrap());
+        scanner
+            .feed(b"<html><head><noscript><meta http-equiv=\"refresh\" content=\"0;url=https://evil.test/\"></noscript></head></html>")
+            .unwrap();
+        let scan = scanner.finish();
+
+        assert_eq!(
+            scan.meta_refresh.as_deref(),
+            Some("0;url=https://evil.test/")
+        );
+    }
+}
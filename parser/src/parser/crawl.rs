@@ -1,165 +1,294 @@
-use std::{collections::HashSet, time::Duration};
+use std::collections::BTreeSet;
 
 use anyhow::Result;
 use futures_util::StreamExt;
-use lazy_static::lazy_static;
-use regex::Regex;
+use reqwest::{
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
+    Method, Response,
+};
+use url::Url;
 
-use crate::{asyncreq, weburl};
+use crate::{
+    asyncreq::{retry::Attempts, Requester},
+    config::Config,
+};
 
 use super::{
-    overlap,
+    brand, cloaking,
+    failure::StatusError,
+    html::{HtmlScanner, PageScan},
+    overlap, redirect,
+    script::ScriptAnalysis,
+    tls,
     vector::{format_bool, format_u8, Vector},
 };
 
-lazy_static! {
-    // Breakpoints where we check the consumed bytes
-    static ref SEPARATOR: HashSet<u8> = HashSet::from_iter("\n\t> ".as_bytes().to_vec());
-// Property
-    static ref PROPERTY_TO_SKIP_PRE: Regex = Regex::new(r#"<(title|head).*"#).unwrap();
-
-    // Html
-    static ref HTML_TO_SKIP_PRE: Regex = Regex::new(r#"<title.*"#).unwrap();
-    static ref HTML_TO_SKIP: Regex = Regex::new(r#"<title.*>.*</\s*title\s*>"#).unwrap();
-    static ref HTML_TITLE_CONTENT: Regex = Regex::new(r#"<title.*>(.*?)</\s*title\s*>"#).unwrap();
+/// Chunks waiting to be tokenized, bounds memory when the tokenizer lags behind
+const CHUNK_BACKLOG: usize = 16;
 
-    static ref HTML_HEAD_SELF_CLOSING: Regex = Regex::new(r#"<head.*/>"#).unwrap();
-    static ref HTML_HEAD_STARTING: Regex = Regex::new(r#"<head.*"#).unwrap();
-    static ref HTML_HEAD_ENDING: Regex = Regex::new(r#"</\s*head.*>"#).unwrap();
+/// Urls discovered on a crawled page (not crawled)
+pub struct CrawledPage {
+    pub links: BTreeSet<String>,
+    /// `Content-Length` of the response scanned, 0 when it had none
+    pub declared_length: usize,
 }
 
-/// Crawls only the url.
-/// Mutates the vector and returns the discovered urls (not crawled)
-pub async fn crawl_page(client: &reqwest::Client, vector: &mut Vector) -> Result<HashSet<String>> {
-    let mut discovered_urls = HashSet::new();
+/// Crawls only the url, and the targets of its meta refreshes when they are followed.
+/// Mutates the vector and returns the discovered urls
+pub async fn crawl_page(
+    requester: &Requester,
+    config: &Config,
+    vector: &mut Vector,
+) -> Result<CrawledPage> {
+    let mut fetched = redirect::fetch(
+        requester,
+        Method::GET,
+        &vector.url,
+        config.page_timeout,
+        config.max_page_bytes,
+        config.max_redirects,
+    )
+    .await?;
+    let mut hops = vec![];
+    let mut attempts = Attempts::default();
+
+    let (scan, page_url, declared_length) = loop {
+        hops.append(&mut fetched.hops);
+        attempts.merge(fetched.attempts);
+        let req = fetched.response;
+        let page_url = req.url().clone();
+        let status = req.status();
+        tls::apply(vector, &req);
+        if !status.is_success() {
+            redirect::apply(vector, hops, attempts, &page_url);
+            return Err(anyhow::Error::from(StatusError(status.as_u16())).context(attempts));
+        }
+
+        let declared_length = declared_length(&req);
+        let scan = scan_page(config, vector, req, page_url.clone()).await?;
+        // Kept from the first page that refreshes, the page it leads to may not
+        cloaking::apply_meta_refresh(vector, &scan, &page_url);
+
+        // Meta refreshes are followed like redirects when asked to, a target that
+        // is unreachable, fails or is not html leaves the page as it is
+        let next = match cloaking::meta_refresh_target(&scan, &page_url) {
+            Some(target) if config.follow_meta_refresh && hops.len() < config.max_redirects => {
+                redirect::fetch(
+                    requester,
+                    Method::GET,
+                    target.as_str(),
+                    config.page_timeout,
+                    config.max_page_bytes,
+                    config.max_redirects - hops.len() - 1,
+                )
+                .await
+                .ok()
+                .filter(|next| is_html_page(&next.response))
+                .map(|next| (target, next))
+            }
+            _ => None,
+        };
+        let Some((target, next)) = next else {
+            redirect::apply(vector, hops, attempts, &page_url);
+            break (scan, page_url, declared_length);
+        };
+
+        hops.push(redirect::meta_refresh_hop(
+            &page_url,
+            status.as_u16(),
+            &target,
+        ));
+        fetched = next;
+    };
+
+    vector.hyprlinks_count += scan.hyprlinks_count;
+    vector.null_hyprlinks_count += scan.null_hyprlinks_count;
+    vector.html_comments_count += scan.html_comments_count;
+    vector.link_tag_count += scan.link_tag_count;
+    vector.navbar_present = format_bool(scan.navbar_present);
+    vector.footer_present = format_bool(scan.footer_present);
 
-    let req = asyncreq::make_req(client.get(&vector.url).timeout(Duration::from_secs(300))).await?;
-    if !req.status().is_success() {
-        anyhow::bail!("failed to fetch url");
+    vector.javascript_count += scan.script_count;
+    vector.samesite_javascript_count += scan.script_count;
+    vector.javascript_reachable_count += scan.script_count;
+
+    // Forms
+    vector.forms_count = scan.forms_count;
+    vector.password_input_count = scan.password_input_count;
+    vector.credit_card_input_count = scan.credit_card_input_count;
+    vector.cvv_input_count = scan.cvv_input_count;
+    vector.otp_input_count = scan.otp_input_count;
+    vector.hidden_input_count = scan.hidden_input_count;
+    vector.empty_form_action_count = scan.empty_form_action_count;
+    vector.mailto_form_action_count = scan.mailto_form_action_count;
+    vector.javascript_form_action_count = scan.javascript_form_action_count;
+    vector.external_form_action_count = scan.external_form_action_count;
+
+    // Handle content within <title> tag
+    if let Some(title) = &scan.title {
+        vector.title_tag_in_head_section = format_bool(scan.title_in_head);
+        vector.title_tag_and_url_overlap = overlap::calculate_overlap(title, &vector.url);
+    }
+    brand::apply_content(&config.brands, vector, &scan);
+    cloaking::apply(vector, &scan, &page_url);
+    for source in scan.inline_scripts.iter().chain([&scan.event_handlers]) {
+        if !source.trim().is_empty() {
+            ScriptAnalysis::analyze(source).apply(vector);
+        }
     }
 
+    Ok(CrawledPage {
+        links: scan.discovered_urls,
+        declared_length,
+    })
+}
+
+/// `Content-Length` of a response, 0 when it has none
+pub fn declared_length(response: &Response) -> usize {
+    response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|val| val.to_str().ok()?.parse::<usize>().ok())
+        .unwrap_or(0)
+}
+
+/// Whether a response is a page that can replace the one refreshing to it
+fn is_html_page(response: &Response) -> bool {
+    response.status().is_success()
+        && response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|val| val.to_str().ok())
+            .is_some_and(|content_type| content_type.to_ascii_lowercase().contains("html"))
+}
+
+/// Checks the headers and tokenizes the body of a page, up to the page body limit
+async fn scan_page(
+    config: &Config,
+    vector: &mut Vector,
+    req: Response,
+    page_url: Url,
+) -> Result<PageScan> {
     // Check headers
-    if let Some(val) = req.headers().get("content-type") {
-        if let Ok(header) = val.to_str() {
-            vector.contenttype_header_contains_text_html =
-                format_bool(header.contains("text/html"));
-            vector.is_utf8_from_header = format_bool(header.contains("utf-8"));
+    let content_type = req
+        .headers()
+        .get("content-type")
+        .and_then(|val| val.to_str().ok())
+        .unwrap_or_default();
+    vector.contenttype_header_contains_text_html = format_bool(content_type.contains("text/html"));
+    vector.is_utf8_from_header = format_bool(content_type.contains("utf-8"));
+
+    // The tokenizer is not Send, so it runs on a blocking thread fed with chunks
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<bytes::Bytes>(CHUNK_BACKLOG);
+    let scanner = tokio::task::spawn_blocking(move || {
+        let mut scanner = HtmlScanner::new(page_url);
+        while let Some(chunk) = rx.blocking_recv() {
+            scanner.feed(&chunk)?;
         }
-    }
+        anyhow::Ok(scanner.finish())
+    });
 
     // Crawl page
+    vector.html_length = 0;
+    vector.html_truncated = 0;
     let mut stream = req.bytes_stream();
-    let mut buffer: Vec<u8> = vec![];
-
-    let mut i = 0;
-    let mut in_head_section = false;
-    while let Some(Ok(chunk)) = stream.next().await {
-        buffer.extend_from_slice(&chunk);
+    while let Some(Ok(mut chunk)) = stream.next().await {
+        let remaining = config.max_page_bytes - vector.html_length;
+        if chunk.len() > remaining {
+            chunk.truncate(remaining);
+            vector.html_truncated = 1;
+        }
         vector.html_length += chunk.len();
 
-        // Consume buffer (HTML as bytes)
-        while i < buffer.len() {
-            // Handle separators
-            if SEPARATOR.contains(&buffer[i]) {
-                let consumed = String::from_utf8(buffer[0..i].to_vec())?;
-
-                // If is a url
-                if let Some(capture) = weburl::HTML_URL.captures(&consumed) {
-                    let potential_url = capture[1].trim();
-                    discovered_urls.insert(potential_url.to_string());
-
-                    // Account for href
-                    if consumed.contains("href") {
-                        vector.hyprlinks_count += 1;
-                        if potential_url.is_empty() || potential_url == "#" {
-                            vector.null_hyprlinks_count += 1;
-                        }
-                    }
-                }
-
-                // If it is a comment
-                if consumed.contains("<!--") {
-                    vector.html_comments_count += 1;
-                }
-
-                // Check for head tag
-                if HTML_HEAD_ENDING.is_match(&consumed) {
-                    in_head_section = false;
-                } else if !in_head_section && HTML_HEAD_STARTING.is_match(&consumed) {
-                    in_head_section = !HTML_HEAD_SELF_CLOSING.is_match(&consumed);
-                }
-
-                if consumed.contains("<nav") {
-                    vector.navbar_present =
-                        format_bool(format_u8(vector.navbar_present) || consumed.contains("<nav"));
-                }
-
-                if consumed.contains("<footer") {
-                    vector.footer_present = format_bool(
-                        format_u8(vector.footer_present) || consumed.contains("<footer"),
-                    );
-                }
-
-                if consumed.contains("<link") {
-                    vector.link_tag_count += 1;
-                }
-
-                if consumed.contains("<script") {
-                    vector.javascript_count += 1;
-                    vector.samesite_javascript_count += 1;
-                    vector.javascript_reachable_count += 1;
-                }
-
-                if HTML_TO_SKIP_PRE.is_match(&consumed) {
-                    if HTML_TO_SKIP.is_match(&consumed) {
-                        // Handle content within <title> tag
-                        if let Some(capture) = HTML_TITLE_CONTENT.captures(&consumed) {
-                            vector.title_tag_in_head_section = format_bool(in_head_section);
-                            vector.title_tag_and_url_overlap =
-                                overlap::calculate_overlap(&capture[1], &vector.url);
-                        }
-
-                        buffer.drain(0..=i);
-                        i = 0;
-                    }
+        // Scanner stopped early, its error is returned below
+        if tx.send(chunk).await.is_err() || format_u8(vector.html_truncated) {
+            break;
+        }
+    }
+    drop(tx);
 
-                    i += 1;
-                    continue;
-                }
+    scanner.await?
+}
 
-                buffer.drain(0..=i);
-                i = 0;
-                continue;
-            }
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
 
-            // Remove repeated spaces
-            if i > 0 && buffer[i] == b' ' && buffer[i - 1] == b' ' {
-                buffer.remove(i - 1);
-                i -= 1;
-            }
+    use crate::Analyzer;
 
-            // Remove spaces around "="
-            if buffer[i] == b'=' {
-                if i + 1 < buffer.len() && buffer[i + 1] == b' ' {
-                    buffer.remove(i + 1);
-                }
-                if i > 0 && buffer[i - 1] == b' ' {
-                    buffer.remove(i - 1);
-                    i -= 1;
-                }
-            }
+    /// Serves a page refreshing to `?to=` at `/`, a 404 at `/missing`, a zip at `/kit.zip`
+    /// and a page without refresh at `/landed`
+    async fn serve() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
 
-            // Remove spaces after opening tags
-            if buffer[i] == b'<' {
-                while i < buffer.len() && buffer[i] == b' ' {
-                    buffer.remove(i);
-                    i -= 1;
-                }
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let read = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]);
+                let path = request.split(' ').nth(1).unwrap_or("/");
+                let (status, content_type, body) = match path {
+                    "/missing" => ("404 Not Found", "text/html", "<p>gone</p>".to_string()),
+                    "/kit.zip" => ("200 OK", "application/zip", "PK".to_string()),
+                    "/landed" => ("200 OK", "text/html", "<title>landed</title>".to_string()),
+                    _ => {
+                        let target = path.trim_start_matches("/?to=");
+                        let body = format!(
+                            "<title>landing</title><noscript><meta http-equiv=refresh content=\"3;url=/{target}\"></noscript>"
+                        );
+                        ("200 OK", "text/html", body)
+                    }
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
             }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn refresh_to_failed_or_binary_target_keeps_page() {
+        let port = serve().await;
+        let analyzer = Analyzer::builder()
+            .probe_hyprlinks(false)
+            .follow_meta_refresh(true)
+            .build()
+            .unwrap();
 
-            i += 1;
+        for target in ["missing", "kit.zip"] {
+            let url = format!("http://127.0.0.1:{port}/?to={target}");
+            let vector = analyzer.analyze(&url).await.unwrap();
+            assert_eq!(vector.final_url, url);
+            assert_eq!(vector.has_meta_refresh, 1);
+            assert_eq!(vector.meta_refresh_redirect_count, 0);
+            assert_eq!(vector.contenttype_header_contains_text_html, 1);
         }
     }
 
-    Ok(discovered_urls)
+    #[tokio::test]
+    async fn followed_refresh_keeps_its_features() {
+        let port = serve().await;
+        let analyzer = Analyzer::builder()
+            .probe_hyprlinks(false)
+            .follow_meta_refresh(true)
+            .build()
+            .unwrap();
+
+        let vector = analyzer
+            .analyze(&format!("http://127.0.0.1:{port}/?to=landed"))
+            .await
+            .unwrap();
+        assert_eq!(vector.final_url, format!("http://127.0.0.1:{port}/landed"));
+        assert_eq!(vector.meta_refresh_redirect_count, 1);
+        assert_eq!(vector.has_meta_refresh, 1);
+        assert_eq!(vector.meta_refresh_delay, 3);
+        assert_eq!(vector.meta_refresh_external, 0);
+    }
 }
@@ -0,0 +1,174 @@
+use std::{error::Error as StdError, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    asyncreq::{retry::Attempts, robots::RobotsDisallowedError},
+    schema::{FeatureType, InvalidVectorError, SCHEMA_VERSION},
+    warc::archive::{InvalidArchivedResponseError, NotArchivedError},
+};
+
+use super::redirect::RedirectLimitError;
+
+/// Stage at which analysing a url failed
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureStage {
+    /// Url could not be parsed
+    Parse,
+    /// Could not connect to the host (dns, refused, reset)
+    Connect,
+    /// TLS handshake or certificate error
+    Tls,
+    /// Request did not complete in time
+    Timeout,
+    /// Server responded with a non-2xx status
+    Status,
+    /// Response body was not valid UTF-8, or an archived response could not be replayed
+    Decode,
+    /// Any other request error (redirects, body read)
+    Request,
+    /// The host's robots.txt disallows fetching the url
+    Disallowed,
+    /// Replaying and the url is not in the archive
+    NotArchived,
+    /// Vector had features that cannot be written, such as NaN
+    Invalid,
+    /// Errors we do not know how to classify
+    Unknown,
+}
+
+impl FailureStage {
+    /// Name as written to the output
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FailureStage::Parse => "parse",
+            FailureStage::Connect => "connect",
+            FailureStage::Tls => "tls",
+            FailureStage::Timeout => "timeout",
+            FailureStage::Status => "status",
+            FailureStage::Decode => "decode",
+            FailureStage::Request => "request",
+            FailureStage::Disallowed => "disallowed",
+            FailureStage::NotArchived => "not_archived",
+            FailureStage::Invalid => "invalid",
+            FailureStage::Unknown => "unknown",
+        }
+    }
+
+    /// Classifies an error returned while analysing a url
+    pub fn of(err: &anyhow::Error) -> Self {
+        if err.downcast_ref::<StatusError>().is_some() {
+            FailureStage::Status
+        } else if err.downcast_ref::<RobotsDisallowedError>().is_some() {
+            FailureStage::Disallowed
+        } else if err.downcast_ref::<NotArchivedError>().is_some() {
+            FailureStage::NotArchived
+        } else if err.downcast_ref::<InvalidArchivedResponseError>().is_some() {
+            FailureStage::Decode
+        } else if err.downcast_ref::<RedirectLimitError>().is_some() {
+            FailureStage::Request
+        } else if err.downcast_ref::<InvalidVectorError>().is_some() {
+            FailureStage::Invalid
+        } else if let Some(e) = err.downcast_ref::<reqwest::Error>() {
+            classify_reqwest(e)
+        } else if err.downcast_ref::<url::ParseError>().is_some() {
+            FailureStage::Parse
+        } else if err.downcast_ref::<std::string::FromUtf8Error>().is_some()
+            || err.downcast_ref::<std::str::Utf8Error>().is_some()
+        {
+            FailureStage::Decode
+        } else {
+            FailureStage::Unknown
+        }
+    }
+}
+
+/// Failure record written alongside the vectors
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Failure {
+    pub schema_version: u32,
+    pub url: String,
+    pub stage: FailureStage,
+    /// Only set when stage is `status`
+    pub status_code: Option<u16>,
+    /// Requests made for the url that failed, 0 when it failed before any was sent
+    #[serde(default)]
+    pub attempts: usize,
+    pub message: String,
+}
+
+/// Returned when the page responds with a non-2xx status
+#[derive(Debug)]
+pub struct StatusError(pub u16);
+
+impl fmt::Display for StatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to fetch url: status {}", self.0)
+    }
+}
+
+impl StdError for StatusError {}
+
+impl Failure {
+    /// Type of each field when written as a table
+    pub fn column_type(_list_field: Option<&str>, name: &str) -> Option<FeatureType> {
+        match name {
+            "schema_version" | "status_code" | "attempts" => Some(FeatureType::Count),
+            "url" | "stage" | "message" => Some(FeatureType::String),
+            _ => None,
+        }
+    }
+
+    /// Classifies an error returned from `generate_vector`
+    pub fn from_error(url: String, err: &anyhow::Error) -> Self {
+        let status_code = err
+            .downcast_ref::<StatusError>()
+            .map(|StatusError(code)| *code);
+
+        Failure {
+            schema_version: SCHEMA_VERSION,
+            url,
+            stage: FailureStage::of(err),
+            status_code,
+            attempts: err
+                .downcast_ref::<Attempts>()
+                .map_or(0, |attempts| attempts.count),
+            message: format!("{err:#}"),
+        }
+    }
+}
+
+/// Classifies a reqwest error, walking its sources to detect TLS failures
+pub fn classify_reqwest(e: &reqwest::Error) -> FailureStage {
+    if e.is_timeout() {
+        return FailureStage::Timeout;
+    }
+    if e.is_builder() {
+        return FailureStage::Parse;
+    }
+    if e.is_connect() {
+        if source_chain_mentions_tls(e) {
+            return FailureStage::Tls;
+        }
+        return FailureStage::Connect;
+    }
+    if e.is_decode() {
+        return FailureStage::Decode;
+    }
+
+    FailureStage::Request
+}
+
+fn source_chain_mentions_tls(e: &(dyn StdError + 'static)) -> bool {
+    let mut source = Some(e);
+    while let Some(err) = source {
+        let msg = err.to_string().to_lowercase();
+        if msg.contains("certificate") || msg.contains("tls") || msg.contains("ssl") {
+            return true;
+        }
+        source = err.source();
+    }
+
+    false
+}
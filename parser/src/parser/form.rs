@@ -0,0 +1,104 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use url::Url;
+
+use crate::weburl::{self, LinkRelation};
+
+lazy_static! {
+    // Matched against the name, id, autocomplete and placeholder of inputs
+    static ref CREDIT_CARD_INPUT: Regex =
+        Regex::new(r"(?i)cc-?number|card.?(num|no)|credit.?card|\bpan\b").unwrap();
+    static ref CVV_INPUT: Regex = Regex::new(r"(?i)cvv|cvc|csc|cc-csc|security.?code").unwrap();
+    static ref OTP_INPUT: Regex =
+        Regex::new(r"(?i)one-time-code|\botp\b|one.?time|verification.?code|sms.?code|2fa|mfa|auth.?code")
+            .unwrap();
+}
+
+/// Kind of credential an input asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    Password,
+    CreditCard,
+    Cvv,
+    Otp,
+    Hidden,
+    Other,
+}
+
+/// Classifies an input from its type and the attributes describing it
+pub fn classify_input(input_type: &str, descriptors: &str) -> InputKind {
+    let input_type = input_type.trim().to_ascii_lowercase();
+    match input_type.as_str() {
+        "password" => return InputKind::Password,
+        "hidden" => return InputKind::Hidden,
+        _ => {}
+    }
+
+    // Cvv before card so "card security code" is a cvv
+    if CVV_INPUT.is_match(descriptors) {
+        InputKind::Cvv
+    } else if CREDIT_CARD_INPUT.is_match(descriptors) {
+        InputKind::CreditCard
+    } else if OTP_INPUT.is_match(descriptors) {
+        InputKind::Otp
+    } else {
+        InputKind::Other
+    }
+}
+
+/// Where a form submits to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormAction {
+    /// No action, an empty one or `#`, submits to the page itself
+    Empty,
+    Mailto,
+    Javascript,
+    /// Different registrable domain than the page, subdomains of its site are internal
+    External,
+    Internal,
+}
+
+/// Classifies a form action against the page it is on
+pub fn classify_action(action: Option<&str>, page_url: &Url) -> FormAction {
+    let action = action.map(str::trim).unwrap_or_default();
+    if action.is_empty() || action == "#" {
+        return FormAction::Empty;
+    }
+
+    let lowercase = action.to_ascii_lowercase();
+    if lowercase.starts_with("mailto:") {
+        return FormAction::Mailto;
+    }
+    if lowercase.starts_with("javascript:") {
+        return FormAction::Javascript;
+    }
+
+    match page_url.join(action) {
+        Ok(target) if weburl::link_relation(&target, page_url) == LinkRelation::ThirdParty => {
+            FormAction::External
+        }
+        _ => FormAction::Internal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_on_the_same_site_are_internal() {
+        let page = Url::parse("https://login.example.com/signin").unwrap();
+        let classify = |action| classify_action(Some(action), &page);
+
+        assert_eq!(
+            classify("https://www.example.com/post"),
+            FormAction::Internal
+        );
+        assert_eq!(classify("/post"), FormAction::Internal);
+        assert_eq!(classify("https://example.co.uk/post"), FormAction::External);
+        assert_eq!(classify("//collector.test/post"), FormAction::External);
+        assert_eq!(classify(" # "), FormAction::Empty);
+        assert_eq!(classify("MAILTO:a@example.com"), FormAction::Mailto);
+        assert_eq!(classify_action(None, &page), FormAction::Empty);
+    }
+}
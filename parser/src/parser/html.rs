@@ -0,0 +1,422 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use html5ever::{
+    local_name,
+    tendril::StrTendril,
+    tokenizer::{
+        states::RawKind, BufferQueue, Tag, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer,
+        TokenizerOpts,
+    },
+};
+
+use url::Url;
+
+use super::form::{self, FormAction, InputKind};
+
+/// Longest title kept, the rest is dropped to bound memory
+const MAX_TITLE_LENGTH: usize = 1024;
+/// Longest meta, alt and label text kept for brand matching
+const MAX_TEXT_LENGTH: usize = 4096;
+/// Inline script text kept across the page
+const MAX_SCRIPT_LENGTH: usize = 256 * 1024;
+/// Iframe sources kept, the rest are only counted
+const MAX_IFRAME_SOURCES: usize = 256;
+
+/// Events of the `on*` handler attributes, other attributes starting with `on` are not scripts
+const HANDLER_EVENTS: &str = "\
+    abort afterprint animationend animationiteration animationstart auxclick beforeinput \
+    beforeprint beforeunload blur cancel canplay canplaythrough change click close \
+    contextmenu copy cuechange cut dblclick drag dragend dragenter dragleave dragover \
+    dragstart drop durationchange emptied ended error focus focusin focusout formdata \
+    hashchange input invalid keydown keypress keyup load loadeddata loadedmetadata \
+    loadstart message mousedown mouseenter mouseleave mousemove mouseout mouseover mouseup \
+    offline online pagehide pageshow paste pause play playing pointercancel pointerdown \
+    pointerenter pointerleave pointermove pointerout pointerover pointerup popstate \
+    progress ratechange reset resize scroll scrollend search seeked seeking select \
+    selectionchange selectstart show stalled storage submit suspend timeupdate toggle \
+    touchcancel touchend touchmove touchstart transitionend unload volumechange waiting \
+    wheel";
+
+/// Features collected while tokenizing a page
+#[derive(Debug, Default)]
+pub struct PageScan {
+    /// Values of every href and src attribute, sorted so vectors are reproducible
+    pub discovered_urls: BTreeSet<String>,
+    pub hyprlinks_count: usize,
+    pub null_hyprlinks_count: usize,
+    pub html_comments_count: usize,
+    pub link_tag_count: usize,
+    pub script_count: usize,
+    pub navbar_present: bool,
+    pub footer_present: bool,
+    /// Text of the first <title>
+    pub title: Option<String>,
+    pub title_in_head: bool,
+
+    // Forms
+    pub forms_count: usize,
+    // Forms by where they submit, see [`form::classify_action`]
+    pub empty_form_action_count: usize,
+    pub mailto_form_action_count: usize,
+    pub javascript_form_action_count: usize,
+    pub external_form_action_count: usize,
+    pub password_input_count: usize,
+    pub credit_card_input_count: usize,
+    pub cvv_input_count: usize,
+    pub otp_input_count: usize,
+    pub hidden_input_count: usize,
+
+    // Text brands are looked for in
+    /// Content of <meta> tags that have a name or property
+    pub meta_text: String,
+    /// Alt text of <img> tags
+    pub image_alt_text: String,
+    /// Text inside <label> tags
+    pub label_text: String,
+
+    // Cloaking
+    /// Content of the first <meta http-equiv="refresh">
+    pub meta_refresh: Option<String>,
+    /// Text of each <script> without a src, up to [`MAX_SCRIPT_LENGTH`] in total
+    pub inline_scripts: Vec<String>,
+    /// Event handler attributes, such as onclick, as `name=value` lines
+    pub event_handlers: String,
+    /// <iframe> and <frame> tags
+    pub iframe_count: usize,
+    /// Frames sized 0 or 1 pixels, or hidden by attribute or inline style
+    pub hidden_iframe_count: usize,
+    /// Src of each frame
+    pub iframe_sources: Vec<String>,
+}
+
+#[derive(Default)]
+struct ScanState {
+    scan: PageScan,
+    in_head: bool,
+    in_title: bool,
+    in_label: bool,
+    in_inline_script: bool,
+    script_length: usize,
+}
+
+struct PageSink {
+    state: RefCell<ScanState>,
+    /// Form actions are resolved against it
+    page_url: Url,
+}
+
+impl TokenSink for PageSink {
+    type Handle = ();
+
+    fn process_token(&self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+        let mut state = self.state.borrow_mut();
+
+        match token {
+            Token::TagToken(tag) => match tag.kind {
+                TagKind::StartTag => return state.start_tag(&tag, &self.page_url),
+                TagKind::EndTag => state.end_tag(&tag),
+            },
+            Token::CharacterTokens(text) if state.in_title => {
+                let title = state.scan.title.get_or_insert_with(String::new);
+                if title.len() < MAX_TITLE_LENGTH {
+                    title.push_str(&text);
+                }
+            }
+            Token::CharacterTokens(text)
+                if state.in_label && state.scan.label_text.len() < MAX_TEXT_LENGTH =>
+            {
+                state.scan.label_text.push_str(&text);
+            }
+            Token::CharacterTokens(text)
+                if state.in_inline_script && state.script_length < MAX_SCRIPT_LENGTH =>
+            {
+                state.script_length += text.len();
+                if let Some(script) = state.scan.inline_scripts.last_mut() {
+                    script.push_str(&text);
+                }
+            }
+            Token::CommentToken(_) => state.scan.html_comments_count += 1,
+            _ => {}
+        }
+
+        TokenSinkResult::Continue
+    }
+}
+
+impl ScanState {
+    fn start_tag(&mut self, tag: &Tag, page_url: &Url) -> TokenSinkResult<()> {
+        for attr in &tag.attrs {
+            let name = &attr.name.local;
+            if is_event_handler(name) && self.script_length < MAX_SCRIPT_LENGTH {
+                self.script_length += name.len() + attr.value.len() + 2;
+                let handlers = &mut self.scan.event_handlers;
+                handlers.push_str(&format!("{name}={}\n", attr.value));
+            }
+            if *name != local_name!("href") && *name != local_name!("src") {
+                continue;
+            }
+
+            let value = attr.value.trim();
+            if !value.is_empty() {
+                self.scan.discovered_urls.insert(value.to_string());
+            }
+            if *name == local_name!("href") {
+                self.scan.hyprlinks_count += 1;
+                if value.is_empty() || value == "#" {
+                    self.scan.null_hyprlinks_count += 1;
+                }
+            }
+        }
+
+        match tag.name {
+            local_name!("head") => self.in_head = !tag.self_closing,
+            local_name!("body") => self.in_head = false,
+            local_name!("nav") => self.scan.navbar_present = true,
+            local_name!("footer") => self.scan.footer_present = true,
+            local_name!("link") => self.scan.link_tag_count += 1,
+            local_name!("form") => {
+                self.scan.forms_count += 1;
+                match form::classify_action(attribute(tag, "action"), page_url) {
+                    FormAction::Empty => self.scan.empty_form_action_count += 1,
+                    FormAction::Mailto => self.scan.mailto_form_action_count += 1,
+                    FormAction::Javascript => self.scan.javascript_form_action_count += 1,
+                    FormAction::External => self.scan.external_form_action_count += 1,
+                    FormAction::Internal => {}
+                }
+            }
+            local_name!("input") => self.input(tag),
+            local_name!("label") => {
+                self.in_label = !tag.self_closing;
+                push_text(&mut self.scan.label_text, "");
+            }
+            local_name!("meta") => {
+                let is_refresh = attribute(tag, "http-equiv")
+                    .is_some_and(|value| value.trim().eq_ignore_ascii_case("refresh"));
+                if is_refresh && self.scan.meta_refresh.is_none() {
+                    self.scan.meta_refresh = attribute(tag, "content").map(str::to_string);
+                }
+                if attribute(tag, "name")
+                    .or(attribute(tag, "property"))
+                    .is_some()
+                {
+                    if let Some(content) = attribute(tag, "content") {
+                        push_text(&mut self.scan.meta_text, content);
+                    }
+                }
+            }
+            local_name!("img") => {
+                if let Some(alt) = attribute(tag, "alt") {
+                    push_text(&mut self.scan.image_alt_text, alt);
+                }
+            }
+            local_name!("script") => {
+                self.scan.script_count += 1;
+                if attribute(tag, "src").is_none() && !tag.self_closing {
+                    self.in_inline_script = true;
+                    self.scan.inline_scripts.push(String::new());
+                }
+                return TokenSinkResult::RawData(RawKind::ScriptData);
+            }
+            local_name!("iframe") | local_name!("frame") => {
+                self.frame(tag);
+                if tag.name == local_name!("iframe") {
+                    return TokenSinkResult::RawData(RawKind::Rawtext);
+                }
+            }
+            local_name!("title") => {
+                // Only the first title counts
+                if self.scan.title.is_none() {
+                    self.in_title = true;
+                    self.scan.title_in_head = self.in_head;
+                }
+                return TokenSinkResult::RawData(RawKind::Rcdata);
+            }
+            local_name!("textarea") => return TokenSinkResult::RawData(RawKind::Rcdata),
+            // <noscript> is left as markup, as a browser without scripting sees it,
+            // so the meta refreshes cloaked pages hide in it are found
+            local_name!("style")
+            | local_name!("xmp")
+            | local_name!("noembed")
+            | local_name!("noframes") => return TokenSinkResult::RawData(RawKind::Rawtext),
+            local_name!("plaintext") => return TokenSinkResult::Plaintext,
+            _ => {}
+        }
+
+        TokenSinkResult::Continue
+    }
+
+    fn input(&mut self, tag: &Tag) {
+        let descriptors = ["name", "id", "autocomplete", "placeholder"]
+            .iter()
+            .filter_map(|name| attribute(tag, name))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        match form::classify_input(attribute(tag, "type").unwrap_or("text"), &descriptors) {
+            InputKind::Password => self.scan.password_input_count += 1,
+            InputKind::CreditCard => self.scan.credit_card_input_count += 1,
+            InputKind::Cvv => self.scan.cvv_input_count += 1,
+            InputKind::Otp => self.scan.otp_input_count += 1,
+            InputKind::Hidden => self.scan.hidden_input_count += 1,
+            InputKind::Other => {}
+        }
+    }
+
+    fn frame(&mut self, tag: &Tag) {
+        self.scan.iframe_count += 1;
+        if let Some(src) = attribute(tag, "src").map(str::trim) {
+            if !src.is_empty() && self.scan.iframe_sources.len() < MAX_IFRAME_SOURCES {
+                self.scan.iframe_sources.push(src.to_string());
+            }
+        }
+
+        let is_tiny = |name| {
+            attribute(tag, name).is_some_and(|value| {
+                matches!(value.trim().trim_end_matches("px").trim(), "0" | "1")
+            })
+        };
+        let style = attribute(tag, "style")
+            .unwrap_or_default()
+            .to_ascii_lowercase()
+            .replace(char::is_whitespace, "");
+        if is_tiny("width")
+            || is_tiny("height")
+            || attribute(tag, "hidden").is_some()
+            || style.contains("display:none")
+            || style.contains("visibility:hidden")
+        {
+            self.scan.hidden_iframe_count += 1;
+        }
+    }
+
+    fn end_tag(&mut self, tag: &Tag) {
+        match tag.name {
+            local_name!("script") => self.in_inline_script = false,
+            local_name!("head") => self.in_head = false,
+            local_name!("label") => self.in_label = false,
+            local_name!("title") => {
+                if self.in_title {
+                    self.in_title = false;
+                    self.scan.title.get_or_insert_with(String::new);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether an attribute is an event handler, such as onclick
+fn is_event_handler(name: &str) -> bool {
+    name.strip_prefix("on").is_some_and(|event| {
+        HANDLER_EVENTS
+            .split_whitespace()
+            .any(|known| known == event)
+    })
+}
+
+/// Appends text after a space, up to [`MAX_TEXT_LENGTH`]
+fn push_text(text: &mut String, value: &str) {
+    if text.len() >= MAX_TEXT_LENGTH {
+        return;
+    }
+
+    if !text.is_empty() && !text.ends_with(' ') {
+        text.push(' ');
+    }
+    text.push_str(value);
+}
+
+/// Value of an attribute on a tag
+fn attribute<'a>(tag: &'a Tag, name: &str) -> Option<&'a str> {
+    tag.attrs
+        .iter()
+        .find(|attr| &*attr.name.local == name)
+        .map(|attr| &*attr.value)
+}
+
+/// Streaming html5 tokenizer, fed bytes as they arrive.
+/// Only the tokenizer state and a partial utf-8 character are kept between chunks.
+pub struct HtmlScanner {
+    tokenizer: Tokenizer<PageSink>,
+    queue: BufferQueue,
+    /// Bytes of a utf-8 character split across chunks
+    partial: Vec<u8>,
+}
+
+impl HtmlScanner {
+    /// Scans the page served from `page_url`
+    pub fn new(page_url: Url) -> Self {
+        let sink = PageSink {
+            state: RefCell::new(ScanState::default()),
+            page_url,
+        };
+
+        HtmlScanner {
+            tokenizer: Tokenizer::new(sink, TokenizerOpts::default()),
+            queue: BufferQueue::default(),
+            partial: vec![],
+        }
+    }
+
+    /// Tokenizes a chunk, erroring if it is not valid utf-8
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<()> {
+        self.partial.extend_from_slice(chunk);
+
+        let valid_up_to = match std::str::from_utf8(&self.partial) {
+            Ok(_) => self.partial.len(),
+            // Character continues in the next chunk
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let text = std::str::from_utf8(&self.partial[..valid_up_to])?;
+        self.queue.push_back(StrTendril::from_slice(text));
+        let _ = self.tokenizer.feed(&self.queue);
+        self.partial.drain(..valid_up_to);
+
+        Ok(())
+    }
+
+    /// Flushes the tokenizer and returns what was found
+    pub fn finish(self) -> PageScan {
+        self.tokenizer.end();
+        self.tokenizer.sink.state.into_inner().scan
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_event_handler_attributes_are_kept() {
+        let mut scanner = HtmlScanner::new(Url::parse("https://example.com/").unwrap());
+        scanner
+            .feed(b"<body onload=\"init()\" one=\"1\" onlyicons><input ONKEYUP=\"send(this)\"></body>")
+            .unwrap();
+        let scan = scanner.finish();
+
+        assert_eq!(scan.event_handlers, "onload=init()\nonkeyup=send(this)\n");
+    }
+
+    #[test]
+    fn every_form_action_is_counted() {
+        let mut scanner = HtmlScanner::new(Url::parse("https://example.com/").unwrap());
+        for _ in 0..300 {
+            scanner
+                .feed(b"<form action=\"https://collector.test/\"></form>")
+                .unwrap();
+        }
+        scanner
+            .feed(b"<form action=\"mailto:a@example.com\"></form><form></form>")
+            .unwrap();
+        let scan = scanner.finish();
+
+        assert_eq!(scan.forms_count, 302);
+        assert_eq!(scan.external_form_action_count, 300);
+        assert_eq!(scan.mailto_form_action_count, 1);
+        assert_eq!(scan.empty_form_action_count, 1);
+    }
+}
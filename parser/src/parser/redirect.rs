@@ -0,0 +1,131 @@
+use std::{error::Error as StdError, fmt, time::Duration};
+
+use anyhow::Result;
+use reqwest::{header::LOCATION, Method, Response, StatusCode};
+use url::Url;
+
+use crate::{
+    asyncreq::{retry::Attempts, Requester},
+    weburl,
+};
+
+use super::vector::{format_bool, RedirectHop, Vector};
+
+/// Returned when a url redirects more times than allowed
+#[derive(Debug)]
+pub struct RedirectLimitError(pub usize);
+
+impl fmt::Display for RedirectLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "too many redirects: more than {}", self.0)
+    }
+}
+
+impl StdError for RedirectLimitError {}
+
+/// Final response and the redirects taken to reach it
+pub struct Fetched {
+    pub response: Response,
+    pub hops: Vec<RedirectHop>,
+    /// Summed over every hop
+    pub attempts: Attempts,
+}
+
+/// Gets, or heads, a url, following redirects by hand so every hop is recorded.
+/// The client must not follow redirects itself, or the chain will be empty.
+/// `body_limit` is how much of a body the caller reads, and so what is recorded.
+pub async fn fetch(
+    requester: &Requester,
+    method: Method,
+    url: &str,
+    timeout: Duration,
+    body_limit: usize,
+    max_redirects: usize,
+) -> Result<Fetched> {
+    let mut current = Url::parse(url)?;
+    let mut hops = vec![];
+    let mut attempts = Attempts::default();
+
+    loop {
+        let response = match method {
+            Method::HEAD => requester.head(&current, timeout).await?,
+            _ => requester.get(&current, timeout, body_limit).await?,
+        };
+        if let Some(hop_attempts) = response.extensions().get::<Attempts>() {
+            attempts.merge(*hop_attempts);
+        }
+
+        let location = match response.status() {
+            StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT => response
+                .headers()
+                .get(LOCATION)
+                .and_then(|val| val.to_str().ok()),
+            _ => None,
+        };
+        // Redirects without a usable location are treated as the final response
+        let Some(next) = location.and_then(|location| current.join(location).ok()) else {
+            return Ok(Fetched {
+                response,
+                hops,
+                attempts,
+            });
+        };
+
+        if hops.len() >= max_redirects {
+            return Err(RedirectLimitError(max_redirects).into());
+        }
+        hops.push(hop(&current, response.status().as_u16(), &next));
+        current = next;
+    }
+}
+
+fn hop(from: &Url, status_code: u16, to: &Url) -> RedirectHop {
+    RedirectHop {
+        url: from.to_string(),
+        status_code,
+        location: to.to_string(),
+        crosses_domain: format_bool(
+            weburl::registrable_domain(from) != weburl::registrable_domain(to),
+        ),
+        downgrades_https: format_bool(from.scheme() == "https" && to.scheme() == "http"),
+        is_shortener: format_bool(weburl::is_url_shortener(from)),
+        is_meta_refresh: 0,
+    }
+}
+
+/// Hop taken by following the meta refresh of a page
+pub fn meta_refresh_hop(from: &Url, status_code: u16, to: &Url) -> RedirectHop {
+    RedirectHop {
+        is_meta_refresh: 1,
+        ..hop(from, status_code, to)
+    }
+}
+
+/// Sets the redirect and retry features of the page from its chain
+pub fn apply(vector: &mut Vector, hops: Vec<RedirectHop>, attempts: Attempts, final_url: &Url) {
+    let initial = Url::parse(&vector.url).ok();
+
+    vector.redirect_count = hops.len();
+    vector.final_url = final_url.to_string();
+    vector.final_domain_differs = format_bool(
+        initial.as_ref().and_then(weburl::registrable_domain)
+            != weburl::registrable_domain(final_url),
+    );
+    vector.is_url_shortener = format_bool(initial.as_ref().is_some_and(weburl::is_url_shortener));
+    for hop in &hops {
+        vector.cross_domain_redirect_count += hop.crosses_domain as usize;
+        vector.https_downgrade_redirect_count += hop.downgrades_https as usize;
+        vector.shortener_redirect_count += hop.is_shortener as usize;
+        vector.meta_refresh_redirect_count += hop.is_meta_refresh as usize;
+    }
+    vector.redirects = hops;
+    vector.fetch_attempts = attempts.count;
+    vector.fetch_error = attempts
+        .last_error
+        .map(|kind| kind.as_str().to_string())
+        .unwrap_or_default();
+}
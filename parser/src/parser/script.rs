@@ -0,0 +1,256 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::weburl;
+
+use super::vector::{format_bool, Vector};
+
+/// Shortest string literal whose entropy is measured
+const MIN_ENTROPY_STRING_LENGTH: usize = 16;
+/// Bytes after a listener is registered in which its body is looked for what it does
+const LISTENER_WINDOW: usize = 1024;
+
+lazy_static! {
+    /// `location = ...` and `location.href = ...`, on any object, but not comparisons
+    static ref LOCATION_ASSIGNMENT: Regex = Regex::new(
+        r#"\blocation(?:\s*\.\s*href)?\s*=\s*(?:["'`]([^"'`\n]*)["'`]|[^=\s])"#
+    )
+    .unwrap();
+    /// `location.replace(...)` and `location.assign(...)`
+    static ref LOCATION_CALL: Regex = Regex::new(
+        r#"\blocation\s*\.\s*(?:replace|assign)\s*\(\s*(?:["'`]([^"'`\n]*)["'`])?"#
+    )
+    .unwrap();
+    static ref EVAL: Regex = Regex::new(r"\beval\s*\(").unwrap();
+    static ref FUNCTION_CONSTRUCTOR: Regex = Regex::new(r"\bFunction\s*\(").unwrap();
+    static ref ATOB: Regex = Regex::new(r"\batob\s*\(").unwrap();
+    static ref UNESCAPE: Regex = Regex::new(r"\bunescape\s*\(").unwrap();
+    static ref DOCUMENT_WRITE: Regex = Regex::new(r"\bdocument\s*\.\s*write(?:ln)?\s*\(").unwrap();
+    static ref STRING_LITERAL: Regex =
+        Regex::new(r#""((?:[^"\\\n]|\\.)*)"|'((?:[^'\\\n]|\\.)*)'|`((?:[^`\\]|\\.)*)`"#).unwrap();
+    /// `\x41\x42...` escapes, or a bare run of hex digits
+    static ref HEX_BLOB: Regex = Regex::new(r"(?:\\x[0-9a-fA-F]{2}){16,}|\b[0-9a-fA-F]{64,}\b").unwrap();
+    static ref BASE64_BLOB: Regex = Regex::new(r"[A-Za-z0-9+/]{100,}={0,2}").unwrap();
+    static ref KEY_LISTENER: Regex = Regex::new(
+        r#"(?:addEventListener|\.on|\.bind)\s*\(\s*["'`](?:keydown|keypress|keyup)["'`]|\bon(?:keydown|keypress|keyup)\s*=[^=]"#
+    )
+    .unwrap();
+    static ref NETWORK_SEND: Regex = Regex::new(
+        r"\bfetch\s*\(|\bXMLHttpRequest\b|\.sendBeacon\s*\(|\bnew\s+WebSocket\s*\(|\bnew\s+Image\s*\("
+    )
+    .unwrap();
+    /// Listener on the context menu, bare mentions such as event name lists do not count
+    static ref CONTEXT_MENU_LISTENER: Regex = Regex::new(
+        r#"(?:addEventListener|\.on|\.bind)\s*\(\s*["'`]contextmenu["'`]|\boncontextmenu\s*=[^=]"#
+    )
+    .unwrap();
+    static ref PREVENTS_DEFAULT: Regex = Regex::new(r"preventDefault\s*\(|return\s+false").unwrap();
+    /// F12, ctrl+shift+i and comparing the outer and inner window size
+    static ref DEVTOOLS_CHECK: Regex = Regex::new(
+        r#"keyCode\s*={2,3}\s*123\b|\bkey\s*={2,3}\s*["']F12["']|outer(?:Width|Height)\s*-\s*(?:window\s*\.\s*)?inner(?:Width|Height)"#
+    )
+    .unwrap();
+}
+
+/// Navigations to another page in a script, with their target when it is a string literal
+pub fn location_targets(script: &str) -> Vec<Option<String>> {
+    LOCATION_ASSIGNMENT
+        .captures_iter(script)
+        .chain(LOCATION_CALL.captures_iter(script))
+        .map(|captures| captures.get(1).map(|target| target.as_str().to_string()))
+        .collect()
+}
+
+/// Obfuscation and abuse signals of script sources, summed over every script
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ScriptAnalysis {
+    pub script_count: usize,
+    pub bytes: usize,
+    /// Shannon entropy of the most random string literal
+    pub max_string_entropy: f32,
+    pub eval_count: usize,
+    pub function_constructor_count: usize,
+    pub atob_count: usize,
+    pub unescape_count: usize,
+    pub document_write_count: usize,
+    pub hex_blob_count: usize,
+    pub base64_blob_count: usize,
+    /// Listens to key presses and sends data over the network from the listener
+    pub keylogger: bool,
+    /// Listens to the context menu and cancels it
+    pub blocks_context_menu: bool,
+    pub blocks_devtools: bool,
+}
+
+impl ScriptAnalysis {
+    pub fn analyze(source: &str) -> Self {
+        let max_string_entropy = STRING_LITERAL
+            .captures_iter(source)
+            .filter_map(|captures| captures.iter().skip(1).flatten().next())
+            .map(|literal| literal.as_str())
+            .filter(|literal| literal.len() >= MIN_ENTROPY_STRING_LENGTH)
+            .map(weburl::calculate_entropy)
+            .fold(0.0, f32::max);
+
+        ScriptAnalysis {
+            script_count: 1,
+            bytes: source.len(),
+            max_string_entropy,
+            eval_count: EVAL.find_iter(source).count(),
+            function_constructor_count: FUNCTION_CONSTRUCTOR.find_iter(source).count(),
+            atob_count: ATOB.find_iter(source).count(),
+            unescape_count: UNESCAPE.find_iter(source).count(),
+            document_write_count: DOCUMENT_WRITE.find_iter(source).count(),
+            hex_blob_count: HEX_BLOB.find_iter(source).count(),
+            // Long runs of hex digits are counted as hex only
+            base64_blob_count: BASE64_BLOB
+                .find_iter(source)
+                .filter(|blob| !blob.as_str().bytes().all(|b| b.is_ascii_hexdigit()))
+                .count(),
+            keylogger: listener_does(&KEY_LISTENER, &NETWORK_SEND, source),
+            blocks_context_menu: listener_does(&CONTEXT_MENU_LISTENER, &PREVENTS_DEFAULT, source),
+            blocks_devtools: DEVTOOLS_CHECK.is_match(source),
+        }
+    }
+
+    /// Adds the signals to the script features of the page
+    pub fn apply(&self, vector: &mut Vector) {
+        vector.analyzed_script_count += self.script_count;
+        vector.analyzed_script_bytes += self.bytes;
+        vector.max_script_string_entropy = vector
+            .max_script_string_entropy
+            .max(self.max_string_entropy);
+        vector.script_eval_count += self.eval_count;
+        vector.script_function_constructor_count += self.function_constructor_count;
+        vector.script_atob_count += self.atob_count;
+        vector.script_unescape_count += self.unescape_count;
+        vector.script_document_write_count += self.document_write_count;
+        vector.script_hex_blob_count += self.hex_blob_count;
+        vector.script_base64_blob_count += self.base64_blob_count;
+        vector.has_keylogger_pattern |= format_bool(self.keylogger);
+        vector.blocks_context_menu |= format_bool(self.blocks_context_menu);
+        vector.blocks_devtools |= format_bool(self.blocks_devtools);
+    }
+}
+
+/// Whether a listener is registered and what it does follows within [`LISTENER_WINDOW`],
+/// so listeners and calls far apart in a bundle are not paired up
+fn listener_does(listener: &Regex, action: &Regex, source: &str) -> bool {
+    listener.find_iter(source).any(|registered| {
+        let mut end = (registered.end() + LISTENER_WINDOW).min(source.len());
+        while !source.is_char_boundary(end) {
+            end -= 1;
+        }
+        action.is_match(&source[registered.start()..end])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_assignments_and_calls() {
+        let targets = location_targets(
+            r#"window.location = "https://a.test/"; location.href='/b'; document.location = next;
+            location.replace("https://c.test/"); location.assign(url);"#,
+        );
+        assert_eq!(
+            targets,
+            [
+                Some("https://a.test/".to_string()),
+                Some("/b".to_string()),
+                None,
+                Some("https://c.test/".to_string()),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn location_comparisons_are_not_navigations() {
+        let script = r#"if (location == "https://a.test/" || location.href === x) {}
+            var relocation = 1; location.hash; allocation = 2;"#;
+        assert!(location_targets(script).is_empty());
+    }
+
+    #[test]
+    fn entropy_of_long_literals_only() {
+        assert_eq!(
+            ScriptAnalysis::analyze(r#"var a = "aZ9$"; "#).max_string_entropy,
+            0.0
+        );
+        let plain = ScriptAnalysis::analyze(r#"var a = "aaaaaaaaaaaaaaaaaaaa";"#);
+        assert_eq!(plain.max_string_entropy, 0.0);
+
+        let random =
+            ScriptAnalysis::analyze(r#"var a = 'q8Zr2LmX0pVt7wKc'; var b = "aaaaaaaaaaaaaaaa";"#);
+        assert_eq!(random.max_string_entropy, 4.0);
+    }
+
+    #[test]
+    fn blobs_are_counted_once() {
+        let hex = "\\x41".repeat(16);
+        let bare_hex = "0123456789abcdef".repeat(4);
+        let base64 = "QUJD".repeat(30);
+        let analysis = ScriptAnalysis::analyze(&format!(
+            r#"var a = "{hex}", b = "{bare_hex}", c = "{base64}==", d = "\x41\x42";"#
+        ));
+
+        assert_eq!(analysis.hex_blob_count, 2);
+        assert_eq!(analysis.base64_blob_count, 1);
+    }
+
+    #[test]
+    fn obfuscation_calls() {
+        let analysis = ScriptAnalysis::analyze(
+            "eval(atob(x)); new Function('a', b)(); unescape(y); document.writeln(z); evaluate(q);",
+        );
+
+        assert_eq!(analysis.eval_count, 1);
+        assert_eq!(analysis.atob_count, 1);
+        assert_eq!(analysis.function_constructor_count, 1);
+        assert_eq!(analysis.unescape_count, 1);
+        assert_eq!(analysis.document_write_count, 1);
+    }
+
+    #[test]
+    fn keylogger_sends_from_the_listener() {
+        let logger = ScriptAnalysis::analyze(
+            "document.addEventListener('keydown', function (e) { fetch('/k?c=' + e.key); });",
+        );
+        assert!(logger.keylogger);
+
+        let far_apart = format!(
+            "el.addEventListener('keyup', onKey);{}fetch('/api/items');",
+            " ".repeat(LISTENER_WINDOW)
+        );
+        assert!(!ScriptAnalysis::analyze(&far_apart).keylogger);
+    }
+
+    #[test]
+    fn context_menu_must_be_cancelled_by_its_listener() {
+        let blocked =
+            ScriptAnalysis::analyze("document.oncontextmenu = function () { return false; };");
+        assert!(blocked.blocks_context_menu);
+        let blocked = ScriptAnalysis::analyze("oncontextmenu=return false\n");
+        assert!(blocked.blocks_context_menu);
+
+        // Event name lists of libraries only mention it
+        let library = ScriptAnalysis::analyze(
+            r#"each("blur focus click dblclick contextmenu".split(" "), fn); if (!ok) return false;"#,
+        );
+        assert!(!library.blocks_context_menu);
+        let compared = ScriptAnalysis::analyze("if (el.oncontextmenu == null) return false;");
+        assert!(!compared.blocks_context_menu);
+    }
+
+    #[test]
+    fn devtools_checks() {
+        assert!(
+            ScriptAnalysis::analyze("if (e.keyCode == 123) e.preventDefault();").blocks_devtools
+        );
+        assert!(ScriptAnalysis::analyze("if (outerWidth - innerWidth > 160) {}").blocks_devtools);
+        assert!(!ScriptAnalysis::analyze("if (e.keyCode == 1234) {}").blocks_devtools);
+    }
+}
@@ -0,0 +1,389 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use futures_util::{stream, StreamExt};
+use url::Url;
+
+use crate::{
+    asyncreq::{retry::Attempts, Requester},
+    config::Config,
+    weburl::{self, LinkRelation},
+};
+
+use super::{
+    crawl,
+    failure::FailureStage,
+    vector::{format_bool, format_u8, Hyprlink, SubPage, Vector},
+};
+
+/// What crawling a sub page found out about its url,
+/// so the hyprlink to it is not fetched a second time
+#[derive(Debug, Clone)]
+pub enum Crawled {
+    /// Html page, read up to the page body limit
+    Page {
+        fetch_attempts: usize,
+        fetch_error: String,
+        certificate_invalid: bool,
+        is_utf8: bool,
+        declared_length: usize,
+        read_length: usize,
+        /// The page body limit was reached
+        truncated: bool,
+    },
+    /// Responded with a non-2xx status, or failed before responding
+    Failed {
+        kind: FailureStage,
+        attempts: Attempts,
+    },
+}
+
+impl Crawled {
+    /// Sets the response features of the hyprlink, as probing it with the
+    /// hyprlink body limit would have
+    pub fn apply(&self, hyprlink: &mut Hyprlink, max_link_bytes: usize) {
+        match self {
+            Crawled::Page {
+                fetch_attempts,
+                fetch_error,
+                certificate_invalid,
+                is_utf8,
+                declared_length,
+                read_length,
+                truncated,
+            } => {
+                hyprlink.fetch_attempts = *fetch_attempts;
+                hyprlink.fetch_error = fetch_error.clone();
+                hyprlink.certificate_invalid = format_bool(*certificate_invalid);
+                hyprlink.is_successful_response = 1;
+                hyprlink.is_utf8_from_header = format_bool(*is_utf8);
+                hyprlink.is_html_from_content_header = 1;
+                hyprlink.is_not_usual_format_from_content_header = 1;
+                hyprlink.resolve_generics();
+                hyprlink.content_length =
+                    usize::max(*declared_length, usize::min(*read_length, max_link_bytes));
+                hyprlink.body_truncated = format_bool(*truncated || *read_length > max_link_bytes);
+            }
+            // A status is a response, the error is that of an earlier attempt
+            Crawled::Failed {
+                kind: FailureStage::Status,
+                attempts,
+            } => {
+                hyprlink.fetch_attempts = attempts.count;
+                hyprlink.fetch_error = attempts
+                    .last_error
+                    .map(|kind| kind.as_str().to_string())
+                    .unwrap_or_default();
+            }
+            Crawled::Failed { kind, attempts } => {
+                hyprlink.request_timed_out = format_bool(*kind == FailureStage::Timeout);
+                hyprlink.fetch_attempts = attempts.count;
+                hyprlink.fetch_error = kind.as_str().to_string();
+            }
+        }
+    }
+}
+
+/// Follows same-site links from the page, breadth first, up to the configured
+/// depth and page budget. Returns what was learned about each crawled url,
+/// keyed without fragment. Sub pages that are not html are left out,
+/// their content type is not kept.
+pub async fn crawl(
+    requester: &Requester,
+    config: &Config,
+    vector: &mut Vector,
+    root_url: &Url,
+    links: &BTreeSet<String>,
+) -> HashMap<String, Crawled> {
+    let mut crawled_urls = HashMap::new();
+    let mut seen = HashSet::new();
+    for url in [vector.url.as_str(), root_url.as_str()] {
+        if let Ok(url) = Url::parse(url) {
+            seen.insert(key(url));
+        }
+    }
+
+    let mut frontier = same_site_links(root_url, links, root_url, &mut seen);
+    let mut budget = config.crawl_pages;
+    for depth in 1..=config.crawl_depth {
+        frontier.truncate(budget);
+        if frontier.is_empty() {
+            break;
+        }
+        budget -= frontier.len();
+
+        // In order, so the sub pages and the next frontier do not depend on timing
+        let crawled = stream::iter(frontier)
+            .map(|(url, referrer)| crawl_subpage(requester, config, url, referrer, depth))
+            .buffered(config.link_concurrency)
+            .collect::<Vec<_>>()
+            .await;
+
+        frontier = vec![];
+        for (subpage, outcome, links) in crawled {
+            if let Some((page_url, links)) = links {
+                frontier.extend(same_site_links(&page_url, &links, root_url, &mut seen));
+            }
+            if let (Some(outcome), Ok(url)) = (outcome, Url::parse(&subpage.url)) {
+                crawled_urls.insert(key(url), outcome);
+            }
+            vector.subpages.push(subpage);
+        }
+    }
+
+    crawled_urls
+}
+
+/// Scans a sub page, handing back what was learned about its url and the links to follow from it
+async fn crawl_subpage(
+    requester: &Requester,
+    config: &Config,
+    url: Url,
+    referrer: String,
+    depth: usize,
+) -> (SubPage, Option<Crawled>, Option<(Url, BTreeSet<String>)>) {
+    let mut sub = Vector::new(url.to_string());
+    let mut subpage = SubPage {
+        url: url.to_string(),
+        depth,
+        referrer,
+        ..SubPage::default()
+    };
+
+    let page = match crawl::crawl_page(requester, config, &mut sub).await {
+        Ok(page) => page,
+        Err(e) => {
+            let kind = FailureStage::of(&e);
+            subpage.final_url = sub.final_url;
+            subpage.fetch_error = kind.as_str().to_string();
+            // Errors scanning the body are not what a probe of the url would see
+            let outcome = is_fetch_failure(kind).then(|| Crawled::Failed {
+                kind,
+                attempts: e.downcast_ref::<Attempts>().copied().unwrap_or_default(),
+            });
+            return (subpage, outcome, None);
+        }
+    };
+
+    subpage.final_url = sub.final_url;
+    subpage.is_html = sub.contenttype_header_contains_text_html;
+    subpage.html_length = sub.html_length;
+    subpage.html_truncated = sub.html_truncated;
+    subpage.hyprlinks_count = sub.hyprlinks_count;
+    subpage.forms_count = sub.forms_count;
+    subpage.password_input_count = sub.password_input_count;
+    subpage.credit_card_input_count = sub.credit_card_input_count;
+    subpage.otp_input_count = sub.otp_input_count;
+    subpage.external_form_action_count = sub.external_form_action_count;
+    subpage.brand_mention_count = sub.brand_mention_count;
+
+    // Only html is followed further
+    if !format_u8(subpage.is_html) {
+        return (subpage, None, None);
+    }
+    let outcome = Crawled::Page {
+        fetch_attempts: sub.fetch_attempts,
+        fetch_error: sub.fetch_error,
+        certificate_invalid: format_u8(sub.certificate_invalid),
+        is_utf8: format_u8(sub.is_utf8_from_header),
+        declared_length: page.declared_length,
+        read_length: subpage.html_length,
+        truncated: format_u8(subpage.html_truncated),
+    };
+    let page_url = Url::parse(&subpage.final_url).ok();
+    (
+        subpage,
+        Some(outcome),
+        page_url.map(|page_url| (page_url, page.links)),
+    )
+}
+
+/// Stages in which fetching the url itself failed
+fn is_fetch_failure(kind: FailureStage) -> bool {
+    matches!(
+        kind,
+        FailureStage::Connect
+            | FailureStage::Tls
+            | FailureStage::Timeout
+            | FailureStage::Status
+            | FailureStage::Request
+            | FailureStage::Disallowed
+            | FailureStage::NotArchived
+    )
+}
+
+/// Resolves links found on a page, keeping unseen same-site pages that may be html
+fn same_site_links(
+    page_url: &Url,
+    links: &BTreeSet<String>,
+    root_url: &Url,
+    seen: &mut HashSet<String>,
+) -> Vec<(Url, String)> {
+    // Pages redirected off the site are not followed
+    if weburl::link_relation(page_url, root_url) == LinkRelation::ThirdParty {
+        return vec![];
+    }
+
+    links
+        .iter()
+        .filter_map(|link| page_url.join(link).ok())
+        .filter(|url| matches!(url.scheme(), "http" | "https"))
+        .filter(|url| weburl::link_relation(url, root_url) != LinkRelation::ThirdParty)
+        .filter(|url| !weburl::has_binary_extension(url))
+        .filter(|url| seen.insert(key(url.clone())))
+        .map(|url| (url, page_url.to_string()))
+        .collect()
+}
+
+/// Links differing only by fragment are the same page
+pub fn key(mut url: Url) -> String {
+    url.set_fragment(None);
+    url.to_string()
+}
+
+/// Sums the page and its sub pages into the site features
+pub fn apply(vector: &mut Vector) {
+    vector.crawl_forms_count = vector.forms_count;
+    vector.crawl_password_input_count = vector.password_input_count;
+    vector.crawl_credit_card_input_count = vector.credit_card_input_count;
+    vector.crawl_external_form_action_count = vector.external_form_action_count;
+    vector.crawl_form_page_count = (vector.forms_count > 0) as usize;
+
+    for subpage in &vector.subpages {
+        if !subpage.fetch_error.is_empty() {
+            vector.crawl_failed_page_count += 1;
+            continue;
+        }
+
+        vector.crawled_page_count += 1;
+        vector.crawl_max_depth = vector.crawl_max_depth.max(subpage.depth);
+        vector.crawl_forms_count += subpage.forms_count;
+        vector.crawl_password_input_count += subpage.password_input_count;
+        vector.crawl_credit_card_input_count += subpage.credit_card_input_count;
+        vector.crawl_external_form_action_count += subpage.external_form_action_count;
+        vector.crawl_form_page_count += (subpage.forms_count > 0) as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use crate::{parser::vector::Vector, Analyzer};
+
+    /// Long sub page, larger than the hyprlink body limit of the tests
+    const LONG: &str = "<p>a page much longer than the hyprlink body limit</p>";
+
+    /// Serves a page linking to sub pages and a missing page, counting requests per path.
+    /// `/unsized` is sent without a content length.
+    async fn serve() -> (u16, Arc<Mutex<HashMap<String, usize>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(Mutex::new(HashMap::new()));
+
+        let counts = Arc::clone(&requests);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let read = stream.read(&mut request).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]);
+                let path = request.split(' ').nth(1).unwrap_or("/").to_string();
+                *counts.lock().unwrap().entry(path.clone()).or_insert(0) += 1;
+
+                let (status, body) = match path.as_str() {
+                    "/" => (
+                        "200 OK",
+                        r#"<a href="/about">about</a><a href="about#team">team</a><a href="/missing">x</a>
+                        <a href="/long">long</a><a href="/unsized">unsized</a>"#,
+                    ),
+                    "/about" => ("200 OK", "<p>about us</p>"),
+                    "/long" | "/unsized" => ("200 OK", LONG),
+                    _ => ("404 Not Found", "<p>gone</p>"),
+                };
+                let length = match path.as_str() {
+                    "/unsized" => String::new(),
+                    _ => format!("content-length: {}\r\n", body.len()),
+                };
+                let response = format!(
+                    "HTTP/1.1 {status}\r\ncontent-type: text/html; charset=utf-8\r\n{length}connection: close\r\n\r\n{body}"
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (port, requests)
+    }
+
+    #[tokio::test]
+    async fn crawled_sub_pages_are_not_probed_again() {
+        let (port, requests) = serve().await;
+        let analyzer = Analyzer::builder().crawl(1, 10).build().unwrap();
+
+        let vector = analyzer
+            .analyze(&format!("http://127.0.0.1:{port}/"))
+            .await
+            .unwrap();
+        assert_eq!(vector.subpages.len(), 4);
+        assert_eq!(requests.lock().unwrap().get("/about"), Some(&1));
+        assert_eq!(requests.lock().unwrap().get("/missing"), Some(&1));
+
+        let hyprlink = |url: &str| {
+            vector
+                .hyprlinks
+                .iter()
+                .find(|hyprlink| hyprlink.url == url)
+                .unwrap()
+        };
+        for url in ["/about", "about#team"] {
+            let about = hyprlink(url);
+            assert_eq!(about.is_successful_response, 1);
+            assert_eq!(about.is_html_from_content_header, 1);
+            assert_eq!(about.is_utf8_from_header, 1);
+            assert_eq!(about.content_length, "<p>about us</p>".len());
+            assert_eq!(about.fetch_attempts, 1);
+        }
+
+        let missing = hyprlink("/missing");
+        assert_eq!(missing.is_successful_response, 0);
+        assert_eq!(missing.fetch_attempts, 1);
+        assert_eq!(missing.fetch_error, "");
+    }
+
+    #[tokio::test]
+    async fn reused_sub_pages_match_probes_past_the_body_limit() {
+        let (port, requests) = serve().await;
+        let url = format!("http://127.0.0.1:{port}/");
+        let analyze = |depth| {
+            let analyzer = Analyzer::builder()
+                .crawl(depth, 10)
+                .max_body_bytes(1024, 16)
+                .build()
+                .unwrap();
+            let url = url.clone();
+            async move { analyzer.analyze(&url).await.unwrap() }
+        };
+
+        let probed = analyze(0).await;
+        let crawled = analyze(1).await;
+        assert_eq!(crawled.subpages.len(), 4);
+        // Once probed, once crawled
+        assert_eq!(requests.lock().unwrap().get("/long"), Some(&2));
+
+        let features = |vector: &Vector, url: &str| {
+            let hyprlink = vector.hyprlinks.iter().find(|h| h.url == url).unwrap();
+            (hyprlink.content_length, hyprlink.body_truncated)
+        };
+        // The declared length wins, without one only the bytes a probe reads count
+        assert_eq!(features(&crawled, "/long"), (LONG.len(), 1));
+        assert_eq!(features(&crawled, "/unsized"), (16, 1));
+        for url in ["/long", "/unsized"] {
+            assert_eq!(features(&crawled, url), features(&probed, url), "{url}");
+        }
+    }
+}
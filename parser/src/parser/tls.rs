@@ -0,0 +1,232 @@
+use reqwest::{tls::TlsInfo, Response};
+use url::Host;
+use x509_parser::{extensions::GeneralName, prelude::X509Certificate};
+
+use crate::{
+    asyncreq::CertificateInvalid,
+    warc::{CapturedAt, PeerCertificate},
+};
+
+use super::vector::{format_bool, Vector};
+
+/// Issuers handing out certificates for free, matched against the lowercase issuer
+const FREE_CAS: &[&str] = &[
+    "let's encrypt",
+    "zerossl",
+    "buypass",
+    "cpanel",
+    "google trust services",
+];
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Sets the certificate features of the page from the response it was served with.
+/// Needs a client built with `tls_info(true)`, or a replayed response with the certificate.
+/// Certificates failing verification only reach here when invalid certificates are accepted.
+/// Features of an earlier response, such as a page left by meta refresh, are cleared.
+pub fn apply(vector: &mut Vector, response: &Response) {
+    vector.has_tls_certificate = 0;
+    vector.cert_issuer = String::new();
+    vector.cert_issuer_is_free_ca = 0;
+    vector.cert_validity_days = 0;
+    vector.cert_age_days = 0;
+    vector.cert_san_count = 0;
+    vector.cert_san_covers_host = 0;
+    vector.cert_self_signed = 0;
+    vector.cert_is_wildcard = 0;
+
+    let extensions = response.extensions();
+    vector.certificate_invalid = format_bool(extensions.get::<CertificateInvalid>().is_some());
+    let Some(der) = extensions
+        .get::<TlsInfo>()
+        .and_then(TlsInfo::peer_certificate)
+        .or(extensions
+            .get::<PeerCertificate>()
+            .map(|cert| cert.0.as_slice()))
+    else {
+        return;
+    };
+    let Ok((_, cert)) = x509_parser::parse_x509_certificate(der) else {
+        return;
+    };
+
+    vector.has_tls_certificate = 1;
+    vector.cert_issuer = issuer(&cert);
+    let lowercase_issuer = vector.cert_issuer.to_lowercase();
+    vector.cert_issuer_is_free_ca =
+        format_bool(FREE_CAS.iter().any(|ca| lowercase_issuer.contains(ca)));
+
+    let validity = cert.validity();
+    let not_before = validity.not_before.timestamp();
+    // Replays are aged from when they were captured, so they stay reproducible
+    let now = extensions
+        .get::<CapturedAt>()
+        .map_or_else(|| chrono::Utc::now().timestamp(), |captured| captured.0);
+    vector.cert_validity_days =
+        ((validity.not_after.timestamp() - not_before) / SECONDS_PER_DAY).max(0) as usize;
+    vector.cert_age_days = ((now - not_before) / SECONDS_PER_DAY).max(0) as usize;
+    vector.cert_self_signed = format_bool(cert.issuer().as_raw() == cert.subject().as_raw());
+
+    let names = san_names(&cert);
+    vector.cert_san_count = names.len();
+    vector.cert_is_wildcard = format_bool(names.iter().any(|name| name.starts_with("*.")));
+    vector.cert_san_covers_host = format_bool(
+        response
+            .url()
+            .host()
+            .is_some_and(|host| names.iter().any(|name| covers(name, &host))),
+    );
+}
+
+/// Organization of the issuer, or its common name
+fn issuer(cert: &X509Certificate) -> String {
+    let issuer = cert.issuer();
+    issuer
+        .iter_organization()
+        .chain(issuer.iter_common_name())
+        .find_map(|attr| attr.as_str().ok())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Dns names and ip addresses of the subject alternative name extension, lowercase
+fn san_names(cert: &X509Certificate) -> Vec<String> {
+    let Ok(Some(san)) = cert.subject_alternative_name() else {
+        return vec![];
+    };
+
+    san.value
+        .general_names
+        .iter()
+        .filter_map(|name| match name {
+            GeneralName::DNSName(dns) => Some(dns.to_ascii_lowercase()),
+            GeneralName::IPAddress(ip) => match ip.len() {
+                4 => Some(std::net::Ipv4Addr::from(<[u8; 4]>::try_from(*ip).ok()?).to_string()),
+                16 => Some(std::net::Ipv6Addr::from(<[u8; 16]>::try_from(*ip).ok()?).to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether a san name matches the host, a wildcard covering exactly one label
+fn covers(name: &str, host: &Host<&str>) -> bool {
+    let host = match host {
+        Host::Domain(domain) => domain.trim_end_matches('.').to_ascii_lowercase(),
+        Host::Ipv4(ip) => return *name == ip.to_string(),
+        Host::Ipv6(ip) => return *name == ip.to_string(),
+    };
+
+    match name.strip_prefix("*.") {
+        Some(parent) => host
+            .split_once('.')
+            .is_some_and(|(label, rest)| !label.is_empty() && rest == parent),
+        None => name == host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+    use tokio_native_tls::{native_tls, TlsAcceptor};
+
+    use crate::{parser::failure::FailureStage, Analyzer};
+
+    const PAGE: &str = "<html><title>x</title></html>";
+
+    /// Serves the page over tls with a self-signed certificate for `name`
+    async fn serve_self_signed(name: &str) -> u16 {
+        let certified = rcgen::generate_simple_self_signed(vec![name.to_string()]).unwrap();
+        let identity = native_tls::Identity::from_pkcs8(
+            certified.cert.pem().as_bytes(),
+            certified.key_pair.serialize_pem().as_bytes(),
+        )
+        .unwrap();
+        let acceptor = TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    // The verifying client hangs up during the handshake
+                    let Ok(mut stream) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let mut request = [0; 4096];
+                    let _ = stream.read(&mut request).await;
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{PAGE}",
+                        PAGE.len()
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                    let _ = stream.shutdown().await;
+                });
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn invalid_certificate_fails_unless_accepted() {
+        let port = serve_self_signed("localhost").await;
+        let analyzer = Analyzer::builder()
+            .probe_hyprlinks(false)
+            .retries(0, std::time::Duration::ZERO)
+            .build()
+            .unwrap();
+
+        let e = analyzer
+            .analyze(&format!("https://localhost:{port}/"))
+            .await
+            .unwrap_err();
+        assert_eq!(FailureStage::of(&e), FailureStage::Tls);
+    }
+
+    #[tokio::test]
+    async fn self_signed_mismatched_certificate() {
+        let port = serve_self_signed("sharkalyze.test").await;
+        let analyzer = Analyzer::builder()
+            .probe_hyprlinks(false)
+            .accept_invalid_certs(true)
+            .retries(0, std::time::Duration::ZERO)
+            .build()
+            .unwrap();
+
+        let vector = analyzer
+            .analyze(&format!("https://127.0.0.1:{port}/"))
+            .await
+            .unwrap();
+        assert_eq!(vector.has_tls_certificate, 1);
+        assert_eq!(vector.cert_self_signed, 1);
+        assert_eq!(vector.cert_san_covers_host, 0);
+        assert_eq!(vector.cert_san_count, 1);
+        assert_eq!(vector.html_length, PAGE.len());
+        assert_eq!(vector.certificate_invalid, 1);
+        assert_eq!(vector.fetch_attempts, 2);
+        assert_eq!(vector.fetch_error, "");
+    }
+
+    #[tokio::test]
+    async fn self_signed_certificate_for_host() {
+        let port = serve_self_signed("localhost").await;
+        let analyzer = Analyzer::builder()
+            .probe_hyprlinks(false)
+            .accept_invalid_certs(true)
+            .build()
+            .unwrap();
+
+        let vector = analyzer
+            .analyze(&format!("https://localhost:{port}/"))
+            .await
+            .unwrap();
+        assert_eq!(vector.cert_self_signed, 1);
+        assert_eq!(vector.cert_san_covers_host, 1);
+        assert_eq!(vector.cert_is_wildcard, 0);
+    }
+}
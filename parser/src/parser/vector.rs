@@ -2,6 +2,8 @@ use lazy_static::lazy_static;
 use regex::bytes::Regex;
 use serde::{Deserialize, Serialize};
 
+use crate::schema::SCHEMA_VERSION;
+
 lazy_static! {
     static ref IMAGE_EXTENSION: Regex =
         Regex::new(r".+\.(jpe?g|png|gif|webp|avif|svg|ico|tiff|bmp)$").unwrap();
@@ -12,15 +14,37 @@ lazy_static! {
         Regex::new(r".+\.(pdf|doc|docx|xls|xlsx|ppt|pptx|odt|ods|odp)$").unwrap();
 }
 
+/// Features of the url string itself
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct UrlLexical {
+    pub url_length: usize,             // ok
+    pub url_digit_ratio: f32,          // ok
+    pub url_special_char_ratio: f32,   // ok
+    pub subdomain_depth: usize,        // ok
+    pub path_depth: usize,             // ok
+    pub query_param_count: usize,      // ok
+    pub url_has_at_symbol: u8,         // ok
+    pub host_is_ip: u8,                // ok
+    pub has_non_standard_port: u8,     // ok
+    pub punycode_label_count: usize,   // ok
+    pub suspicious_token_count: usize, // ok
+    pub tld: String,                   // ok
+}
+
 /// Discovered hyprlinks
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Hyprlink {
     // Url
-    pub url: String,                // ok
-    pub is_ssl_https: u8,           // ok
-    pub url_entropy: f32,           // ok
+    pub url: String,      // ok
+    pub is_ssl_https: u8, // ok
+    pub url_entropy: f32, // ok
+    #[serde(flatten)]
+    pub lexical: UrlLexical,
     pub is_samesite: u8,            // ok
     pub is_external: u8,            // ok
+    pub is_same_origin: u8,         // ok
+    pub is_same_site: u8,           // ok
+    pub is_third_party: u8,         // ok
     pub is_successful_response: u8, // ok
     pub request_timed_out: u8,      // ok
 
@@ -68,6 +92,13 @@ pub struct Hyprlink {
 
     // Content length
     pub content_length: usize, // ok
+
+    pub fetch_attempts: usize,   // ok
+    pub fetch_error: String,     // ok
+    pub certificate_invalid: u8, // ok
+
+    pub body_truncated: u8, // ok
+    pub is_head_probe: u8,  // ok
 }
 
 impl Hyprlink {
@@ -85,8 +116,12 @@ impl Hyprlink {
             url,
             is_ssl_https: 0,
             url_entropy: 0f32,
+            lexical: UrlLexical::default(),
             is_samesite: 0,
             is_external: 0,
+            is_same_origin: 0,
+            is_same_site: 0,
+            is_third_party: 0,
             is_successful_response: 0,
             request_timed_out: 0,
             is_html: 0,
@@ -132,6 +167,11 @@ impl Hyprlink {
             is_multipart_from_content_header: 0,
             is_not_usual_format_from_content_header: 0,
             content_length: 0,
+            fetch_attempts: 0,
+            fetch_error: String::new(),
+            certificate_invalid: 0,
+            body_truncated: 0,
+            is_head_probe: 0,
         }
     }
 
@@ -150,26 +190,86 @@ impl Hyprlink {
     }
 }
 
+/// Redirect taken while fetching the page
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RedirectHop {
+    /// Url that responded with the redirect
+    pub url: String, // ok
+    pub status_code: u16, // ok
+    /// Resolved location it redirected to
+    pub location: String, // ok
+    /// Location is on another registrable domain
+    pub crosses_domain: u8, // ok
+    /// https url redirecting to http
+    pub downgrades_https: u8, // ok
+    pub is_shortener: u8, // ok
+    /// Page redirected with a meta refresh rather than a 3xx
+    pub is_meta_refresh: u8, // ok
+}
+
+/// Brand named in the text of the page
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BrandMention {
+    pub brand: String,          // ok
+    pub title_count: usize,     // ok
+    pub meta_count: usize,      // ok
+    pub image_alt_count: usize, // ok
+    pub label_count: usize,     // ok
+    /// Page is on a domain of the brand
+    pub owns_domain: u8, // ok
+}
+
+/// Same-site page reached by following links from the page
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SubPage {
+    pub url: String, // ok
+    /// Links followed from the page to reach it
+    pub depth: usize, // ok
+    /// Page the link was found on
+    pub referrer: String, // ok
+    pub final_url: String, // ok
+    /// Failure stage, empty when it was crawled
+    pub fetch_error: String, // ok
+    pub is_html: u8, // ok
+    pub html_length: usize, // ok
+    pub html_truncated: u8, // ok
+    pub hyprlinks_count: usize, // ok
+    pub forms_count: usize, // ok
+    pub password_input_count: usize, // ok
+    pub credit_card_input_count: usize, // ok
+    pub otp_input_count: usize, // ok
+    pub external_form_action_count: usize, // ok
+    pub brand_mention_count: usize, // ok
+}
+
 /// Vector structure that is generated
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Vector {
+    pub schema_version: u32,
+
     // Url
-    pub url: String,                               // ok
-    pub is_ssl_https: u8,                          // ok
-    pub url_entropy: f32,                          // ok
+    pub url: String,      // ok
+    pub is_ssl_https: u8, // ok
+    pub url_entropy: f32, // ok
+    #[serde(flatten)]
+    pub lexical: UrlLexical,
     pub is_utf8_from_header: u8,                   // ok
     pub contenttype_header_contains_text_html: u8, // ok
 
     // Links
-    pub hyprlinks: Vec<Hyprlink>,          // ok
-    pub hyprlinks_count: usize,            // ok
-    pub external_link_count: usize,        // ok
-    pub samesite_link_count: usize,        // ok
-    pub external_samesite_link_ratio: f32, // ok
+    pub hyprlinks: Vec<Hyprlink>,                   // ok
+    pub hyprlinks_count: usize,                     // ok
+    pub external_link_count: usize,                 // ok
+    pub samesite_link_count: usize,                 // ok
+    pub same_origin_link_count: usize,              // ok
+    pub same_site_link_count: usize,                // ok
+    pub third_party_link_count: usize,              // ok
+    pub external_samesite_link_ratio: f32,          // ok
+    pub external_samesite_link_ratio_undefined: u8, // ok
     /// <a href="" /> or <a href="#" />
     pub null_hyprlinks_count: usize, // ok
     /// <link />
-    pub link_tag_count: usize, // ok
+    pub link_tag_count: usize,      // ok
 
     // HTML
     pub html_length: usize,            // ok
@@ -181,31 +281,133 @@ pub struct Vector {
     pub footer_present: u8,            // ok
 
     // JavaScript
-    pub javascript_count: usize,                 // ok
-    pub samesite_javascript_count: usize,        // ok
-    pub external_javascript_count: usize,        // ok
-    pub external_samesite_javascript_ratio: f32, // ok
+    pub javascript_count: usize,                          // ok
+    pub samesite_javascript_count: usize,                 // ok
+    pub external_javascript_count: usize,                 // ok
+    pub external_samesite_javascript_ratio: f32,          // ok
+    pub external_samesite_javascript_ratio_undefined: u8, // ok
     /// 200 OK response?
     pub javascript_reachable_count: usize, // ok
     /// Not 200 OK response?
     pub javascript_unreachable_count: usize, // ok
-    pub javascript_reachable_ratio: f32,         // ok
+    pub javascript_reachable_ratio: f32,                  // ok
+    pub javascript_reachable_ratio_undefined: u8,         // ok
+
+    // Forms
+    pub forms_count: usize,                  // ok
+    pub password_input_count: usize,         // ok
+    pub credit_card_input_count: usize,      // ok
+    pub cvv_input_count: usize,              // ok
+    pub otp_input_count: usize,              // ok
+    pub hidden_input_count: usize,           // ok
+    pub external_form_action_count: usize,   // ok
+    pub mailto_form_action_count: usize,     // ok
+    pub empty_form_action_count: usize,      // ok
+    pub javascript_form_action_count: usize, // ok
+
+    // Redirects
+    pub redirects: Vec<RedirectHop>,           // ok
+    pub redirect_count: usize,                 // ok
+    pub final_url: String,                     // ok
+    pub final_domain_differs: u8,              // ok
+    pub cross_domain_redirect_count: usize,    // ok
+    pub https_downgrade_redirect_count: usize, // ok
+    pub is_url_shortener: u8,                  // ok
+    pub shortener_redirect_count: usize,       // ok
+
+    // Brand
+    pub host_unicode: String,           // ok
+    pub nearest_brand: String,          // ok
+    pub brand_min_edit_distance: usize, // ok
+    pub is_brand_domain: u8,            // ok
+    pub typosquat_brand: u8,            // ok
+    pub confusable_brand_match: u8,     // ok
+    pub brand_in_domain: u8,            // ok
+    pub brand_in_subdomain: u8,         // ok
+    pub brand_in_path: u8,              // ok
+
+    // Brand content
+    pub brand_mentions: Vec<BrandMention>, // ok
+    pub brand_mention_count: usize,        // ok
+    pub mentioned_brand_count: usize,      // ok
+    pub brand_domain_mismatch: u8,         // ok
+
+    // TLS
+    pub has_tls_certificate: u8,    // ok
+    pub cert_issuer: String,        // ok
+    pub cert_issuer_is_free_ca: u8, // ok
+    pub cert_validity_days: usize,  // ok
+    pub cert_age_days: usize,       // ok
+    pub cert_san_count: usize,      // ok
+    pub cert_san_covers_host: u8,   // ok
+    pub cert_self_signed: u8,       // ok
+    pub cert_is_wildcard: u8,       // ok
+    pub certificate_invalid: u8,    // ok
+
+    // Retries
+    pub fetch_attempts: usize, // ok
+    pub fetch_error: String,   // ok
+
+    pub html_truncated: u8, // ok
+
+    // Site
+    pub subpages: Vec<SubPage>,                  // ok
+    pub crawled_page_count: usize,               // ok
+    pub crawl_failed_page_count: usize,          // ok
+    pub crawl_max_depth: usize,                  // ok
+    pub crawl_forms_count: usize,                // ok
+    pub crawl_password_input_count: usize,       // ok
+    pub crawl_credit_card_input_count: usize,    // ok
+    pub crawl_external_form_action_count: usize, // ok
+    pub crawl_form_page_count: usize,            // ok
+
+    // Cloaking
+    pub has_meta_refresh: u8,               // ok
+    pub meta_refresh_delay: usize,          // ok
+    pub meta_refresh_external: u8,          // ok
+    pub meta_refresh_redirect_count: usize, // ok
+    pub js_redirect_count: usize,           // ok
+    pub js_redirect_external: u8,           // ok
+    pub iframe_count: usize,                // ok
+    pub hidden_iframe_count: usize,         // ok
+    pub external_iframe_count: usize,       // ok
+
+    // Script analysis
+    pub analyzed_script_count: usize,             // ok
+    pub analyzed_script_bytes: usize,             // ok
+    pub max_script_string_entropy: f32,           // ok
+    pub script_eval_count: usize,                 // ok
+    pub script_function_constructor_count: usize, // ok
+    pub script_atob_count: usize,                 // ok
+    pub script_unescape_count: usize,             // ok
+    pub script_document_write_count: usize,       // ok
+    pub script_hex_blob_count: usize,             // ok
+    pub script_base64_blob_count: usize,          // ok
+    pub has_keylogger_pattern: u8,                // ok
+    pub blocks_context_menu: u8,                  // ok
+    pub blocks_devtools: u8,                      // ok
 }
 
 impl Vector {
     /// Initializes a new Vector with default values
     pub fn new(url: String) -> Self {
         Vector {
+            schema_version: SCHEMA_VERSION,
             url,
             is_ssl_https: 0,
             url_entropy: 0f32,
+            lexical: UrlLexical::default(),
             is_utf8_from_header: 0,
             contenttype_header_contains_text_html: 0,
             hyprlinks: vec![],
             hyprlinks_count: 0,
             external_link_count: 0,
             samesite_link_count: 0,
+            same_origin_link_count: 0,
+            same_site_link_count: 0,
+            third_party_link_count: 0,
             external_samesite_link_ratio: 0f32,
+            external_samesite_link_ratio_undefined: 0,
             null_hyprlinks_count: 0,
             link_tag_count: 0,
             html_length: 0,
@@ -218,11 +420,120 @@ impl Vector {
             samesite_javascript_count: 0,
             external_javascript_count: 0,
             external_samesite_javascript_ratio: 0f32,
+            external_samesite_javascript_ratio_undefined: 0,
             javascript_reachable_count: 0,
             javascript_unreachable_count: 0,
             javascript_reachable_ratio: 0f32,
+            javascript_reachable_ratio_undefined: 0,
+            forms_count: 0,
+            password_input_count: 0,
+            credit_card_input_count: 0,
+            cvv_input_count: 0,
+            otp_input_count: 0,
+            hidden_input_count: 0,
+            external_form_action_count: 0,
+            mailto_form_action_count: 0,
+            empty_form_action_count: 0,
+            javascript_form_action_count: 0,
+            redirects: vec![],
+            redirect_count: 0,
+            final_url: String::new(),
+            final_domain_differs: 0,
+            cross_domain_redirect_count: 0,
+            https_downgrade_redirect_count: 0,
+            is_url_shortener: 0,
+            shortener_redirect_count: 0,
+            host_unicode: String::new(),
+            nearest_brand: String::new(),
+            brand_min_edit_distance: 0,
+            is_brand_domain: 0,
+            typosquat_brand: 0,
+            confusable_brand_match: 0,
+            brand_in_domain: 0,
+            brand_in_subdomain: 0,
+            brand_in_path: 0,
+            brand_mentions: vec![],
+            brand_mention_count: 0,
+            mentioned_brand_count: 0,
+            brand_domain_mismatch: 0,
+            has_tls_certificate: 0,
+            cert_issuer: String::new(),
+            cert_issuer_is_free_ca: 0,
+            cert_validity_days: 0,
+            cert_age_days: 0,
+            cert_san_count: 0,
+            cert_san_covers_host: 0,
+            cert_self_signed: 0,
+            cert_is_wildcard: 0,
+            certificate_invalid: 0,
+            fetch_attempts: 0,
+            fetch_error: String::new(),
+            html_truncated: 0,
+            subpages: vec![],
+            crawled_page_count: 0,
+            crawl_failed_page_count: 0,
+            crawl_max_depth: 0,
+            crawl_forms_count: 0,
+            crawl_password_input_count: 0,
+            crawl_credit_card_input_count: 0,
+            crawl_external_form_action_count: 0,
+            crawl_form_page_count: 0,
+            has_meta_refresh: 0,
+            meta_refresh_delay: 0,
+            meta_refresh_external: 0,
+            meta_refresh_redirect_count: 0,
+            js_redirect_count: 0,
+            js_redirect_external: 0,
+            iframe_count: 0,
+            hidden_iframe_count: 0,
+            external_iframe_count: 0,
+            analyzed_script_count: 0,
+            analyzed_script_bytes: 0,
+            max_script_string_entropy: 0.0,
+            script_eval_count: 0,
+            script_function_constructor_count: 0,
+            script_atob_count: 0,
+            script_unescape_count: 0,
+            script_document_write_count: 0,
+            script_hex_blob_count: 0,
+            script_base64_blob_count: 0,
+            has_keylogger_pattern: 0,
+            blocks_context_menu: 0,
+            blocks_devtools: 0,
         }
     }
+
+    /// Computes every ratio from the counts
+    pub fn resolve_ratios(&mut self) {
+        (
+            self.external_samesite_link_ratio,
+            self.external_samesite_link_ratio_undefined,
+        ) = ratio(self.external_link_count, self.samesite_link_count);
+        (
+            self.javascript_reachable_ratio,
+            self.javascript_reachable_ratio_undefined,
+        ) = ratio(
+            self.javascript_reachable_count,
+            self.javascript_unreachable_count,
+        );
+        (
+            self.external_samesite_javascript_ratio,
+            self.external_samesite_javascript_ratio_undefined,
+        ) = ratio(
+            self.external_javascript_count,
+            self.samesite_javascript_count,
+        );
+    }
+}
+
+/// Divides two counts.
+/// When the denominator is 0 the ratio is undefined, so 0 is returned with the undefined flag set.
+pub fn ratio(numerator: usize, denominator: usize) -> (f32, u8) {
+    if denominator == 0 {
+        return (0f32, 1);
+    }
+
+    (numerator as f32 / denominator as f32, 0)
 }
 
 pub fn format_bool(b: bool) -> u8 {
@@ -0,0 +1,1188 @@
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::parser::vector::Vector;
+
+/// Bumped whenever a field of [`Vector`](crate::Vector) or one of its nested records,
+/// such as [`Hyprlink`](crate::Hyprlink), is added, removed or changes meaning
+pub const SCHEMA_VERSION: u32 = 20;
+
+/// How a feature is encoded
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureType {
+    /// `u8` that is either 0 or 1
+    Flag,
+    /// Non negative integer
+    Count,
+    /// `f32` quotient of two counts
+    Ratio,
+    /// Any other `f32`
+    Float,
+    String,
+    /// Nested records, written as a child table
+    List,
+}
+
+/// Record a feature belongs to
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureTable {
+    Page,
+    Hyprlink,
+    Redirect,
+    BrandMention,
+    SubPage,
+}
+
+impl FeatureTable {
+    /// Field of the page holding the records, `None` for the page itself
+    fn list_field(self) -> Option<&'static str> {
+        match self {
+            FeatureTable::Page => None,
+            FeatureTable::Hyprlink => Some("hyprlinks"),
+            FeatureTable::Redirect => Some("redirects"),
+            FeatureTable::BrandMention => Some("brand_mentions"),
+            FeatureTable::SubPage => Some("subpages"),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+pub struct Feature {
+    pub name: &'static str,
+    pub table: FeatureTable,
+    #[serde(rename = "type")]
+    pub kind: FeatureType,
+    /// Inclusive, `None` when unbounded
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub description: &'static str,
+    /// Module that computes it
+    pub module: &'static str,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Catalog {
+    pub schema_version: u32,
+    pub features: Vec<Feature>,
+}
+
+const fn feature(
+    table: FeatureTable,
+    name: &'static str,
+    kind: FeatureType,
+    module: &'static str,
+    description: &'static str,
+) -> Feature {
+    let (min, max) = match kind {
+        FeatureType::Flag => (Some(0.0), Some(1.0)),
+        FeatureType::Count | FeatureType::Ratio | FeatureType::Float => (Some(0.0), None),
+        FeatureType::String | FeatureType::List => (None, None),
+    };
+
+    Feature {
+        name,
+        table,
+        kind,
+        min,
+        max,
+        description,
+        module,
+    }
+}
+
+const fn bounded(mut feature: Feature, min: f64, max: f64) -> Feature {
+    feature.min = Some(min);
+    feature.max = Some(max);
+    feature
+}
+
+use FeatureTable::{BrandMention as B, Hyprlink as L, Page as P, Redirect as R, SubPage as S};
+use FeatureType::{Count, Flag, Float, List, Ratio, String as Str};
+
+const FEATURES: &[Feature] = &[
+    // Page
+    feature(
+        P,
+        "schema_version",
+        Count,
+        "schema",
+        "Version of this catalog the record follows",
+    ),
+    feature(P, "url", Str, "parser", "Submitted url"),
+    feature(P, "is_ssl_https", Flag, "parser", "Url uses https"),
+    bounded(
+        feature(
+            P,
+            "url_entropy",
+            Float,
+            "weburl",
+            "Shannon entropy of the url bytes, in bits",
+        ),
+        0.0,
+        8.0,
+    ),
+    feature(
+        P,
+        "url_length",
+        Count,
+        "weburl::lexical",
+        "Characters in the url",
+    ),
+    bounded(
+        feature(
+            P,
+            "url_digit_ratio",
+            Float,
+            "weburl::lexical",
+            "Share of the url characters that are digits",
+        ),
+        0.0,
+        1.0,
+    ),
+    bounded(
+        feature(
+            P,
+            "url_special_char_ratio",
+            Float,
+            "weburl::lexical",
+            "Share of the url characters that are neither alphanumeric nor `:`, `/` or `.`",
+        ),
+        0.0,
+        1.0,
+    ),
+    feature(
+        P,
+        "subdomain_depth",
+        Count,
+        "weburl::lexical",
+        "Host labels before the registrable domain",
+    ),
+    feature(
+        P,
+        "path_depth",
+        Count,
+        "weburl::lexical",
+        "Non-empty path segments",
+    ),
+    feature(
+        P,
+        "query_param_count",
+        Count,
+        "weburl::lexical",
+        "Query parameters",
+    ),
+    feature(
+        P,
+        "url_has_at_symbol",
+        Flag,
+        "weburl::lexical",
+        "Url contains `@`",
+    ),
+    feature(
+        P,
+        "host_is_ip",
+        Flag,
+        "weburl::lexical",
+        "Host is an ip address",
+    ),
+    feature(
+        P,
+        "has_non_standard_port",
+        Flag,
+        "weburl::lexical",
+        "Url has a port other than the default for its scheme",
+    ),
+    feature(
+        P,
+        "punycode_label_count",
+        Count,
+        "weburl::lexical",
+        "Host labels that are punycode encoded",
+    ),
+    feature(
+        P,
+        "suspicious_token_count",
+        Count,
+        "weburl::lexical",
+        "Words such as login, verify or pay found in the url",
+    ),
+    feature(
+        P,
+        "tld",
+        Str,
+        "weburl::lexical",
+        "Last label of the host, empty for ip addresses",
+    ),
+    feature(
+        P,
+        "is_utf8_from_header",
+        Flag,
+        "parser::crawl",
+        "Content-Type header declares utf-8",
+    ),
+    feature(
+        P,
+        "contenttype_header_contains_text_html",
+        Flag,
+        "parser::crawl",
+        "Content-Type header declares text/html",
+    ),
+    feature(
+        P,
+        "hyprlinks",
+        List,
+        "parser",
+        "Probed hyprlinks, see the hyprlink table",
+    ),
+    feature(
+        P,
+        "hyprlinks_count",
+        Count,
+        "parser::html",
+        "href attributes in the page",
+    ),
+    feature(
+        P,
+        "external_link_count",
+        Count,
+        "parser",
+        "Probed hyprlinks on another registrable domain",
+    ),
+    feature(
+        P,
+        "samesite_link_count",
+        Count,
+        "parser",
+        "Probed hyprlinks on the same registrable domain",
+    ),
+    feature(
+        P,
+        "same_origin_link_count",
+        Count,
+        "parser",
+        "Probed hyprlinks with the same origin as the page",
+    ),
+    feature(
+        P,
+        "same_site_link_count",
+        Count,
+        "parser",
+        "Probed hyprlinks on another origin of the same registrable domain",
+    ),
+    feature(
+        P,
+        "third_party_link_count",
+        Count,
+        "parser",
+        "Probed hyprlinks on another registrable domain",
+    ),
+    feature(
+        P,
+        "external_samesite_link_ratio",
+        Ratio,
+        "parser::vector",
+        "external_link_count / samesite_link_count, 0 when undefined",
+    ),
+    feature(
+        P,
+        "external_samesite_link_ratio_undefined",
+        Flag,
+        "parser::vector",
+        "Denominator of external_samesite_link_ratio is 0",
+    ),
+    feature(
+        P,
+        "null_hyprlinks_count",
+        Count,
+        "parser::html",
+        "href attributes that are empty or #",
+    ),
+    feature(P, "link_tag_count", Count, "parser::html", "<link> tags"),
+    feature(
+        P,
+        "html_length",
+        Count,
+        "parser::crawl",
+        "Bytes of html read, at most the page body limit",
+    ),
+    feature(
+        P,
+        "html_comments_count",
+        Count,
+        "parser::html",
+        "Html comments",
+    ),
+    feature(
+        P,
+        "title_tag_in_head_section",
+        Flag,
+        "parser::html",
+        "<title> is inside <head>",
+    ),
+    bounded(
+        feature(
+            P,
+            "title_tag_and_url_overlap",
+            Ratio,
+            "parser::overlap",
+            "Characters shared by the title and url",
+        ),
+        0.0,
+        1.0,
+    ),
+    feature(
+        P,
+        "navbar_present",
+        Flag,
+        "parser::html",
+        "Page has a <nav>",
+    ),
+    feature(
+        P,
+        "footer_present",
+        Flag,
+        "parser::html",
+        "Page has a <footer>",
+    ),
+    feature(
+        P,
+        "javascript_count",
+        Count,
+        "parser",
+        "Scripts in the page and probed javascript hyprlinks",
+    ),
+    feature(
+        P,
+        "samesite_javascript_count",
+        Count,
+        "parser",
+        "Same site scripts",
+    ),
+    feature(
+        P,
+        "external_javascript_count",
+        Count,
+        "parser",
+        "External scripts",
+    ),
+    feature(
+        P,
+        "external_samesite_javascript_ratio",
+        Ratio,
+        "parser::vector",
+        "external_javascript_count / samesite_javascript_count, 0 when undefined",
+    ),
+    feature(
+        P,
+        "external_samesite_javascript_ratio_undefined",
+        Flag,
+        "parser::vector",
+        "Denominator of external_samesite_javascript_ratio is 0",
+    ),
+    feature(
+        P,
+        "javascript_reachable_count",
+        Count,
+        "parser",
+        "Scripts that responded with 2xx",
+    ),
+    feature(
+        P,
+        "javascript_unreachable_count",
+        Count,
+        "parser",
+        "Scripts that did not respond with 2xx",
+    ),
+    feature(
+        P,
+        "javascript_reachable_ratio",
+        Ratio,
+        "parser::vector",
+        "javascript_reachable_count / javascript_unreachable_count, 0 when undefined",
+    ),
+    feature(
+        P,
+        "javascript_reachable_ratio_undefined",
+        Flag,
+        "parser::vector",
+        "Denominator of javascript_reachable_ratio is 0",
+    ),
+    feature(P, "forms_count", Count, "parser::html", "<form> tags"),
+    feature(
+        P,
+        "password_input_count",
+        Count,
+        "parser::form",
+        "Inputs of type password",
+    ),
+    feature(
+        P,
+        "credit_card_input_count",
+        Count,
+        "parser::form",
+        "Inputs named or autocompleted like a card number",
+    ),
+    feature(
+        P,
+        "cvv_input_count",
+        Count,
+        "parser::form",
+        "Inputs named or autocompleted like a card security code",
+    ),
+    feature(
+        P,
+        "otp_input_count",
+        Count,
+        "parser::form",
+        "Inputs named or autocompleted like a one time code",
+    ),
+    feature(
+        P,
+        "hidden_input_count",
+        Count,
+        "parser::form",
+        "Inputs of type hidden",
+    ),
+    feature(
+        P,
+        "external_form_action_count",
+        Count,
+        "parser::form",
+        "Forms submitting to another site",
+    ),
+    feature(
+        P,
+        "mailto_form_action_count",
+        Count,
+        "parser::form",
+        "Forms submitting to a mailto: action",
+    ),
+    feature(
+        P,
+        "empty_form_action_count",
+        Count,
+        "parser::form",
+        "Forms without an action, or with an empty or # one",
+    ),
+    feature(
+        P,
+        "javascript_form_action_count",
+        Count,
+        "parser::form",
+        "Forms submitting to a javascript: action",
+    ),
+    feature(
+        P,
+        "redirects",
+        List,
+        "parser::redirect",
+        "Redirects taken to reach the page, see the redirect table",
+    ),
+    feature(
+        P,
+        "redirect_count",
+        Count,
+        "parser::redirect",
+        "Redirects taken to reach the page",
+    ),
+    feature(
+        P,
+        "final_url",
+        Str,
+        "parser::redirect",
+        "Url the page was fetched from after redirects",
+    ),
+    feature(
+        P,
+        "final_domain_differs",
+        Flag,
+        "parser::redirect",
+        "Final url is on another registrable domain than the submitted one",
+    ),
+    feature(
+        P,
+        "cross_domain_redirect_count",
+        Count,
+        "parser::redirect",
+        "Redirects to another registrable domain",
+    ),
+    feature(
+        P,
+        "https_downgrade_redirect_count",
+        Count,
+        "parser::redirect",
+        "Redirects from https to http",
+    ),
+    feature(
+        P,
+        "is_url_shortener",
+        Flag,
+        "parser::redirect",
+        "Submitted url is on a known url shortener",
+    ),
+    feature(
+        P,
+        "shortener_redirect_count",
+        Count,
+        "parser::redirect",
+        "Redirects issued by a known url shortener",
+    ),
+    feature(P, "host_unicode", Str, "parser::brand", "Host of the final url with punycode decoded"),
+    feature(P, "nearest_brand", Str, "parser::brand", "Brand whose domain is closest to the final registrable domain, empty without brands"),
+    feature(P, "brand_min_edit_distance", Count, "parser::brand", "Levenshtein distance from the final registrable domain to the nearest brand domain, 0 without brands"),
+    feature(P, "is_brand_domain", Flag, "parser::brand", "Final registrable domain is owned by a listed brand"),
+    feature(P, "typosquat_brand", Flag, "parser::brand", "Label of the final registrable domain is within an edit per 4 characters of the label of a brand domain, 5 or more characters long, or the same label under another suffix, on a domain no brand owns"),
+    feature(P, "confusable_brand_match", Flag, "parser::brand", "Final registrable domain looks the same as a brand domain once confusable characters are mapped, without being it"),
+    feature(P, "brand_in_domain", Flag, "parser::brand", "Brand name as a whole label or hyphenated word of the final registrable domain, which the brand does not own"),
+    feature(P, "brand_in_subdomain", Flag, "parser::brand", "Brand name as a whole label or hyphenated word of the subdomain of a domain the brand does not own"),
+    feature(P, "brand_in_path", Flag, "parser::brand", "Brand name as a whole word of the path or query of a domain the brand does not own"),
+    feature(P, "brand_mentions", List, "parser::brand", "Brands named in the title, meta tags, image alt text or labels, see the brand mention table"),
+    feature(P, "brand_mention_count", Count, "parser::brand", "Times a brand is named in the title, meta tags, image alt text or labels"),
+    feature(P, "mentioned_brand_count", Count, "parser::brand", "Distinct brands named"),
+    feature(P, "brand_domain_mismatch", Flag, "parser::brand", "A brand is named while the page is not on one of its domains"),
+    feature(P, "has_tls_certificate", Flag, "parser::tls", "Page was served over tls with a peer certificate, the other certificate features are 0 otherwise"),
+    feature(P, "cert_issuer", Str, "parser::tls", "Organization, or common name, of the certificate issuer"),
+    feature(P, "cert_issuer_is_free_ca", Flag, "parser::tls", "Issuer hands out certificates for free, such as Let's Encrypt"),
+    feature(P, "cert_validity_days", Count, "parser::tls", "Days between the certificate's not before and not after"),
+    feature(P, "cert_age_days", Count, "parser::tls", "Days since the certificate's not before"),
+    feature(P, "cert_san_count", Count, "parser::tls", "Dns names and ip addresses in the subject alternative names"),
+    feature(P, "cert_san_covers_host", Flag, "parser::tls", "A subject alternative name matches the final host"),
+    feature(P, "cert_self_signed", Flag, "parser::tls", "Issuer is the same as the subject"),
+    feature(P, "cert_is_wildcard", Flag, "parser::tls", "A subject alternative name is a wildcard"),
+    feature(P, "certificate_invalid", Flag, "parser::tls", "Certificate failed verification and was accepted on a second request, 0 unless invalid certificates are accepted"),
+    feature(P, "fetch_attempts", Count, "asyncreq::retry", "Requests sent for the page across its redirects, including retries"),
+    feature(P, "fetch_error", Str, "asyncreq::retry", "Failure stage of the last failed attempt, empty when none failed"),
+    feature(P, "html_truncated", Flag, "parser::crawl", "Page was longer than the body limit, only html_length bytes were read"),
+    feature(P, "subpages", List, "parser::site", "Same-site pages reached by following links, see the sub page table, empty unless crawling"),
+    feature(P, "crawled_page_count", Count, "parser::site", "Sub pages fetched and scanned"),
+    feature(P, "crawl_failed_page_count", Count, "parser::site", "Sub pages that could not be fetched"),
+    feature(P, "crawl_max_depth", Count, "parser::site", "Deepest sub page scanned, in links followed from the page"),
+    feature(P, "crawl_forms_count", Count, "parser::site", "<form> elements on the page and its sub pages"),
+    feature(P, "crawl_password_input_count", Count, "parser::site", "Password inputs on the page and its sub pages"),
+    feature(P, "crawl_credit_card_input_count", Count, "parser::site", "Credit card inputs on the page and its sub pages"),
+    feature(P, "crawl_external_form_action_count", Count, "parser::site", "Forms on the page and its sub pages submitting to another site"),
+    feature(P, "crawl_form_page_count", Count, "parser::site", "Pages, the page included, with at least one form"),
+    feature(P, "has_meta_refresh", Flag, "parser::cloaking", "Page, or the first page refreshing to it, has a <meta http-equiv=\"refresh\">"),
+    feature(P, "meta_refresh_delay", Count, "parser::cloaking", "Seconds before the first meta refresh, 0 without one"),
+    feature(P, "meta_refresh_external", Flag, "parser::cloaking", "First meta refresh goes to another registrable domain than the page refreshing"),
+    feature(P, "meta_refresh_redirect_count", Count, "parser::crawl", "Meta refreshes followed to reach the page, included in redirect_count"),
+    feature(P, "js_redirect_count", Count, "parser::cloaking", "Assignments to location, location.href, and calls to location.replace or location.assign in inline scripts"),
+    feature(P, "js_redirect_external", Flag, "parser::cloaking", "An inline script navigates to a literal url on another registrable domain"),
+    feature(P, "iframe_count", Count, "parser::html", "<iframe> and <frame> tags"),
+    feature(P, "hidden_iframe_count", Count, "parser::html", "Frames sized 0 or 1 pixels, or hidden by attribute or inline style"),
+    feature(P, "external_iframe_count", Count, "parser::cloaking", "Frames loading another registrable domain"),
+    feature(P, "analyzed_script_count", Count, "parser::script", "Inline scripts, event handler attributes and fetched javascript hyprlinks analysed"),
+    feature(P, "analyzed_script_bytes", Count, "parser::script", "Bytes of script analysed, bounded by the inline script and hyprlink body limits"),
+    feature(P, "max_script_string_entropy", Float, "parser::script", "Highest Shannon entropy of a string literal of 16 or more characters"),
+    feature(P, "script_eval_count", Count, "parser::script", "Calls to eval"),
+    feature(P, "script_function_constructor_count", Count, "parser::script", "Calls to Function, with or without new"),
+    feature(P, "script_atob_count", Count, "parser::script", "Calls to atob"),
+    feature(P, "script_unescape_count", Count, "parser::script", "Calls to unescape"),
+    feature(P, "script_document_write_count", Count, "parser::script", "Calls to document.write or document.writeln"),
+    feature(P, "script_hex_blob_count", Count, "parser::script", "Runs of 16 or more \\x escapes, or 64 or more hex digits"),
+    feature(P, "script_base64_blob_count", Count, "parser::script", "Runs of 100 or more base64 characters that are not only hex digits"),
+    feature(P, "has_keylogger_pattern", Flag, "parser::script", "A script listens to key presses and sends requests from the listener"),
+    feature(P, "blocks_context_menu", Flag, "parser::script", "A script listens to contextmenu and prevents the default from the listener"),
+    feature(P, "blocks_devtools", Flag, "parser::script", "A script checks for F12 or compares the outer and inner window size to detect devtools"),
+    // Hyprlink
+    feature(L, "url", Str, "parser", "Hyprlink as written in the page"),
+    feature(L, "is_ssl_https", Flag, "parser", "Resolved url uses https"),
+    bounded(
+        feature(
+            L,
+            "url_entropy",
+            Float,
+            "weburl",
+            "Shannon entropy of the resolved url bytes, in bits",
+        ),
+        0.0,
+        8.0,
+    ),
+    feature(
+        L,
+        "url_length",
+        Count,
+        "weburl::lexical",
+        "Characters in the url",
+    ),
+    bounded(
+        feature(
+            L,
+            "url_digit_ratio",
+            Float,
+            "weburl::lexical",
+            "Share of the url characters that are digits",
+        ),
+        0.0,
+        1.0,
+    ),
+    bounded(
+        feature(
+            L,
+            "url_special_char_ratio",
+            Float,
+            "weburl::lexical",
+            "Share of the url characters that are neither alphanumeric nor `:`, `/` or `.`",
+        ),
+        0.0,
+        1.0,
+    ),
+    feature(
+        L,
+        "subdomain_depth",
+        Count,
+        "weburl::lexical",
+        "Host labels before the registrable domain",
+    ),
+    feature(
+        L,
+        "path_depth",
+        Count,
+        "weburl::lexical",
+        "Non-empty path segments",
+    ),
+    feature(
+        L,
+        "query_param_count",
+        Count,
+        "weburl::lexical",
+        "Query parameters",
+    ),
+    feature(
+        L,
+        "url_has_at_symbol",
+        Flag,
+        "weburl::lexical",
+        "Url contains `@`",
+    ),
+    feature(
+        L,
+        "host_is_ip",
+        Flag,
+        "weburl::lexical",
+        "Host is an ip address",
+    ),
+    feature(
+        L,
+        "has_non_standard_port",
+        Flag,
+        "weburl::lexical",
+        "Url has a port other than the default for its scheme",
+    ),
+    feature(
+        L,
+        "punycode_label_count",
+        Count,
+        "weburl::lexical",
+        "Host labels that are punycode encoded",
+    ),
+    feature(
+        L,
+        "suspicious_token_count",
+        Count,
+        "weburl::lexical",
+        "Words such as login, verify or pay found in the url",
+    ),
+    feature(
+        L,
+        "tld",
+        Str,
+        "weburl::lexical",
+        "Last label of the host, empty for ip addresses",
+    ),
+    feature(
+        L,
+        "is_samesite",
+        Flag,
+        "parser",
+        "Hyprlink is on the same registrable domain, same origin or same site",
+    ),
+    feature(
+        L,
+        "is_external",
+        Flag,
+        "parser",
+        "Hyprlink is on another registrable domain, same as is_third_party",
+    ),
+    feature(
+        L,
+        "is_same_origin",
+        Flag,
+        "weburl",
+        "Hyprlink has the same scheme, host and port as the page",
+    ),
+    feature(
+        L,
+        "is_same_site",
+        Flag,
+        "weburl",
+        "Hyprlink is on another origin of the page's registrable domain, such as a subdomain",
+    ),
+    feature(
+        L,
+        "is_third_party",
+        Flag,
+        "weburl",
+        "Hyprlink is on another registrable domain (eTLD+1) than the page",
+    ),
+    feature(
+        L,
+        "is_successful_response",
+        Flag,
+        "parser",
+        "Responded with 2xx",
+    ),
+    feature(L, "request_timed_out", Flag, "parser", "Request timed out"),
+    feature(
+        L,
+        "is_html",
+        Flag,
+        "parser::vector",
+        "Html by url or header",
+    ),
+    feature(
+        L,
+        "is_javascript",
+        Flag,
+        "parser::vector",
+        "Javascript by url or header",
+    ),
+    feature(
+        L,
+        "is_json",
+        Flag,
+        "parser::vector",
+        "Json by url or header",
+    ),
+    feature(L, "is_css", Flag, "parser::vector", "Css by url or header"),
+    feature(
+        L,
+        "is_image",
+        Flag,
+        "parser::vector",
+        "Image by url or header",
+    ),
+    feature(
+        L,
+        "is_video",
+        Flag,
+        "parser::vector",
+        "Video by url or header",
+    ),
+    feature(
+        L,
+        "is_audio",
+        Flag,
+        "parser::vector",
+        "Audio by url or header",
+    ),
+    feature(
+        L,
+        "url_type_is_known",
+        Flag,
+        "parser::vector",
+        "Type could be identified",
+    ),
+    feature(
+        L,
+        "is_html_from_url",
+        Flag,
+        "parser::vector",
+        "Url ends with .htm or .html",
+    ),
+    feature(
+        L,
+        "is_javascript_from_url",
+        Flag,
+        "parser::vector",
+        "Url ends with .js",
+    ),
+    feature(
+        L,
+        "is_json_from_url",
+        Flag,
+        "parser::vector",
+        "Url ends with .json",
+    ),
+    feature(
+        L,
+        "is_css_from_url",
+        Flag,
+        "parser::vector",
+        "Url ends with .css",
+    ),
+    feature(
+        L,
+        "is_image_from_url",
+        Flag,
+        "parser::vector",
+        "Url has an image extension",
+    ),
+    feature(
+        L,
+        "is_video_from_url",
+        Flag,
+        "parser::vector",
+        "Url has a video extension",
+    ),
+    feature(
+        L,
+        "is_audio_from_url",
+        Flag,
+        "parser::vector",
+        "Url has an audio extension",
+    ),
+    feature(
+        L,
+        "is_document_from_url",
+        Flag,
+        "parser::vector",
+        "Url has a document extension",
+    ),
+    feature(
+        L,
+        "cannot_identify_from_url",
+        Flag,
+        "parser::vector",
+        "Url has no known extension",
+    ),
+    feature(
+        L,
+        "is_utf8_from_header",
+        Flag,
+        "parser",
+        "Content-Type header declares utf-8",
+    ),
+    feature(
+        L,
+        "is_html_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is text/html",
+    ),
+    feature(
+        L,
+        "is_javascript_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is text/javascript",
+    ),
+    feature(
+        L,
+        "is_json_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is application/json",
+    ),
+    feature(
+        L,
+        "is_css_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is text/css",
+    ),
+    feature(
+        L,
+        "is_xml_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is text/xml",
+    ),
+    feature(
+        L,
+        "is_csv_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is text/csv",
+    ),
+    feature(
+        L,
+        "is_plain_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is text/plain",
+    ),
+    feature(
+        L,
+        "is_image_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is image/*",
+    ),
+    feature(
+        L,
+        "is_video_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is video/*",
+    ),
+    feature(
+        L,
+        "is_audio_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is audio/*",
+    ),
+    feature(
+        L,
+        "is_xtoken_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is x-token/*",
+    ),
+    feature(
+        L,
+        "is_message_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is message/*",
+    ),
+    feature(
+        L,
+        "is_multipart_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is multipart/*",
+    ),
+    feature(
+        L,
+        "is_not_usual_format_from_content_header",
+        Flag,
+        "parser",
+        "Content-Type is none of image, video, audio, x-token, message or multipart",
+    ),
+    feature(
+        L,
+        "content_length",
+        Count,
+        "parser",
+        "Bytes in the response body, the larger of its declared length and the bytes read",
+    ),
+    feature(L, "fetch_attempts", Count, "asyncreq::retry", "Requests sent for the hyprlink across its redirects, including retries"),
+    feature(L, "fetch_error", Str, "asyncreq::retry", "Failure stage of the last failed attempt, empty when none failed"),
+    feature(L, "certificate_invalid", Flag, "asyncreq", "Certificate failed verification and was accepted on a second request, 0 unless invalid certificates are accepted"),
+    feature(L, "body_truncated", Flag, "parser", "Body was longer than the body limit and was not read past it"),
+    feature(L, "is_head_probe", Flag, "parser", "Non-html resource probed for its headers only, content_length is the declared length"),
+    // Redirect
+    feature(
+        R,
+        "url",
+        Str,
+        "parser::redirect",
+        "Url that responded with the redirect",
+    ),
+    feature(
+        R,
+        "status_code",
+        Count,
+        "parser::redirect",
+        "Redirect status",
+    ),
+    feature(
+        R,
+        "location",
+        Str,
+        "parser::redirect",
+        "Resolved url it redirected to",
+    ),
+    feature(
+        R,
+        "crosses_domain",
+        Flag,
+        "parser::redirect",
+        "Location is on another registrable domain",
+    ),
+    feature(
+        R,
+        "downgrades_https",
+        Flag,
+        "parser::redirect",
+        "Redirect from https to http",
+    ),
+    feature(
+        R,
+        "is_shortener",
+        Flag,
+        "parser::redirect",
+        "Url is on a known url shortener",
+    ),
+    feature(
+        R,
+        "is_meta_refresh",
+        Flag,
+        "parser::crawl",
+        "Page redirected with a meta refresh rather than a 3xx",
+    ),
+    // Brand mention
+    feature(B, "brand", Str, "parser::brand", "Brand from the brand list"),
+    feature(B, "title_count", Count, "parser::brand", "Mentions in the <title>"),
+    feature(B, "meta_count", Count, "parser::brand", "Mentions in <meta> content"),
+    feature(B, "image_alt_count", Count, "parser::brand", "Mentions in <img> alt text"),
+    feature(B, "label_count", Count, "parser::brand", "Mentions in <label> text"),
+    feature(B, "owns_domain", Flag, "parser::brand", "Page is on a domain of the brand"),
+    // Sub page
+    feature(S, "url", Str, "parser::site", "Resolved link that was followed"),
+    feature(S, "depth", Count, "parser::site", "Links followed from the page to reach it"),
+    feature(S, "referrer", Str, "parser::site", "Page the link was found on"),
+    feature(S, "final_url", Str, "parser::site", "Url after redirects"),
+    feature(S, "fetch_error", Str, "parser::site", "Failure stage, empty when it was crawled"),
+    feature(S, "is_html", Flag, "parser::site", "Content-Type header contains text/html"),
+    feature(S, "html_length", Count, "parser::crawl", "Bytes of html read, at most the page body limit"),
+    feature(S, "html_truncated", Flag, "parser::crawl", "Page was longer than the body limit"),
+    feature(S, "hyprlinks_count", Count, "parser::html", "href attributes in the page"),
+    feature(S, "forms_count", Count, "parser::html", "<form> tags"),
+    feature(S, "password_input_count", Count, "parser::form", "Inputs of type password"),
+    feature(S, "credit_card_input_count", Count, "parser::form", "Inputs named or autocompleted like a card number"),
+    feature(S, "otp_input_count", Count, "parser::form", "Inputs named or autocompleted like a one time code"),
+    feature(S, "external_form_action_count", Count, "parser::form", "Forms submitting to another site"),
+    feature(S, "brand_mention_count", Count, "parser::brand", "Times a brand is named in the title, meta tags, image alt text or labels"),
+];
+
+/// Returned when a vector has features that cannot be written
+#[derive(Debug)]
+pub struct InvalidVectorError(pub Vec<String>);
+
+impl fmt::Display for InvalidVectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "non-finite features: {}", self.0.join(", "))
+    }
+}
+
+impl std::error::Error for InvalidVectorError {}
+
+/// Checks that every float feature of the vector and its nested records is finite.
+/// Non-finite floats serialize to null, which is what is looked for.
+pub fn validate(vector: &Vector) -> Result<(), InvalidVectorError> {
+    let page = serde_json::to_value(vector).unwrap_or(Value::Null);
+    let mut invalid = vec![];
+
+    for feature in FEATURES
+        .iter()
+        .filter(|f| matches!(f.kind, FeatureType::Float | FeatureType::Ratio))
+    {
+        let (finite, name) = match feature.table.list_field() {
+            None => (is_number(&page, feature.name), feature.name.to_string()),
+            Some(field) => {
+                let mut records = page
+                    .get(field)
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten();
+                (
+                    records.all(|r| is_number(r, feature.name)),
+                    format!("{field}.{}", feature.name),
+                )
+            }
+        };
+        if !finite {
+            invalid.push(name);
+        }
+    }
+
+    if invalid.is_empty() {
+        return Ok(());
+    }
+    Err(InvalidVectorError(invalid))
+}
+
+fn is_number(record: &Value, name: &str) -> bool {
+    record.get(name).is_some_and(Value::is_number)
+}
+
+/// Type of a feature of the page, or of the records in one of its lists such as `hyprlinks`
+pub fn feature_type(list_field: Option<&str>, name: &str) -> Option<FeatureType> {
+    FEATURES
+        .iter()
+        .find(|feature| feature.table.list_field() == list_field && feature.name == name)
+        .map(|feature| feature.kind)
+}
+
+/// Every feature in the order it is written
+pub fn catalog() -> Catalog {
+    Catalog {
+        schema_version: SCHEMA_VERSION,
+        features: FEATURES.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use crate::parser::{
+        failure::Failure,
+        vector::{BrandMention, Hyprlink, RedirectHop, SubPage, Vector},
+    };
+
+    use super::{FeatureTable, FEATURES};
+
+    fn keys(record: &Value) -> Vec<&str> {
+        record
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect()
+    }
+
+    fn catalog_names(table: FeatureTable) -> Vec<&'static str> {
+        FEATURES
+            .iter()
+            .filter(|feature| feature.table == table)
+            .map(|feature| feature.name)
+            .collect()
+    }
+
+    #[test]
+    fn catalog_follows_the_serialized_records() {
+        let mut vector = Vector::new("https://example.com/".to_string());
+        vector
+            .hyprlinks
+            .push(Hyprlink::new("https://example.com/a".to_string()));
+        vector.redirects.push(RedirectHop {
+            url: "http://example.com/".to_string(),
+            status_code: 301,
+            location: "https://example.com/".to_string(),
+            crosses_domain: 0,
+            downgrades_https: 1,
+            is_shortener: 0,
+            is_meta_refresh: 0,
+        });
+        vector.brand_mentions.push(BrandMention {
+            brand: "example".to_string(),
+            title_count: 1,
+            meta_count: 0,
+            image_alt_count: 0,
+            label_count: 0,
+            owns_domain: 1,
+        });
+        vector.subpages.push(SubPage::default());
+        let page = serde_json::to_value(&vector).unwrap();
+
+        assert_eq!(keys(&page), catalog_names(FeatureTable::Page));
+        for table in [
+            FeatureTable::Hyprlink,
+            FeatureTable::Redirect,
+            FeatureTable::BrandMention,
+            FeatureTable::SubPage,
+        ] {
+            let field = table.list_field().unwrap();
+            assert_eq!(keys(&page[field][0]), catalog_names(table), "{field}");
+        }
+    }
+
+    #[test]
+    fn every_failure_field_is_typed() {
+        let failure = Failure::from_error(
+            "https://example.com/".to_string(),
+            &anyhow::anyhow!("failed"),
+        );
+        for name in keys(&serde_json::to_value(&failure).unwrap()) {
+            assert!(Failure::column_type(None, name).is_some(), "{name}");
+        }
+    }
+}
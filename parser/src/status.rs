@@ -1,3 +1,44 @@
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// How progress is reported during a scan
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ProgressMode {
+    /// Interactive progress bar
+    Bar,
+    /// One json object per finished url on stderr
+    Json,
+}
+
+/// Emitted in json progress mode
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    Progress {
+        url: &'a str,
+        ok: bool,
+        succeeded: usize,
+        failed: usize,
+        total: usize,
+    },
+    Done {
+        succeeded: usize,
+        failed: usize,
+        total: usize,
+        elapsed_secs: f64,
+        output: &'a str,
+        failures: &'a str,
+    },
+}
+
+impl ProgressEvent<'_> {
+    pub fn emit(&self) {
+        if let Ok(line) = serde_json::to_string(self) {
+            eprintln!("{line}");
+        }
+    }
+}
+
 pub fn format_progress_string(ok: usize, err: usize, total: usize) -> String {
     format!(
         "\x1b[32m[Ok: {ok}]\x1b[0m \x1b[31m[Err: {err}]\x1b[0m \x1b[33m[Left: {}]\x1b[0m",
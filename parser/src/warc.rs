@@ -0,0 +1,236 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+
+pub mod archive;
+pub mod writer;
+
+pub use archive::Archive;
+pub use writer::WarcWriter;
+
+/// Peer certificate (DER) of a replayed response, set as a response extension
+#[derive(Debug, Clone)]
+pub struct PeerCertificate(pub Vec<u8>);
+
+/// Unix time a replayed response was captured at, set as a response extension
+#[derive(Debug, Clone, Copy)]
+pub struct CapturedAt(pub i64);
+
+/// Record of a web archive, see https://iipc.github.io/warc-specifications/
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// `WARC/1.0` or `WARC/1.1`
+    pub version: String,
+    pub headers: Vec<(String, String)>,
+    pub block: Vec<u8>,
+}
+
+impl Record {
+    /// Value of a header, names are case insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// `WARC-Type`, such as response or request
+    pub fn record_type(&self) -> Option<&str> {
+        self.header("WARC-Type")
+    }
+
+    /// Serializes the record, with the separator that follows it
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = format!("{}\r\n", self.version).into_bytes();
+        for (name, value) in &self.headers {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                continue;
+            }
+            bytes.extend_from_slice(format!("{name}: {value}\r\n").as_bytes());
+        }
+        bytes.extend_from_slice(format!("Content-Length: {}\r\n\r\n", self.block.len()).as_bytes());
+        bytes.extend_from_slice(&self.block);
+        bytes.extend_from_slice(b"\r\n\r\n");
+        bytes
+    }
+
+    pub fn target_uri(&self) -> Option<&str> {
+        // Some writers wrap the uri in angle brackets
+        self.header("WARC-Target-URI")
+            .map(|uri| uri.trim_start_matches('<').trim_end_matches('>'))
+    }
+}
+
+/// Reads records from a `.warc` file, or a `.warc.gz` compressed per record or as a whole
+pub struct RecordReader<R> {
+    reader: R,
+}
+
+impl RecordReader<Box<dyn BufRead>> {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("failed to open archive: {}", path.display()))?;
+        let reader: Box<dyn BufRead> = if path.extension().is_some_and(|ext| ext == "gz") {
+            Box::new(BufReader::new(MultiGzDecoder::new(file)))
+        } else {
+            Box::new(BufReader::new(file))
+        };
+
+        Ok(RecordReader { reader })
+    }
+}
+
+impl<R: BufRead> RecordReader<R> {
+    pub fn new(reader: R) -> Self {
+        RecordReader { reader }
+    }
+
+    /// Next record, `None` at the end of the archive
+    pub fn next_record(&mut self) -> Result<Option<Record>> {
+        // Records are separated by blank lines
+        let version = loop {
+            let Some(line) = self.read_line()? else {
+                return Ok(None);
+            };
+            if !line.is_empty() {
+                break line;
+            }
+        };
+        if !version.starts_with("WARC/") {
+            anyhow::bail!("expected a warc version line, found: {version}");
+        }
+
+        let mut headers = vec![];
+        loop {
+            let line = self
+                .read_line()?
+                .context("archive ended inside record headers")?;
+            if line.is_empty() {
+                break;
+            }
+
+            let (name, value) = line
+                .split_once(':')
+                .with_context(|| format!("malformed warc header: {line}"))?;
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+
+        let mut record = Record {
+            version,
+            headers,
+            block: vec![],
+        };
+        let length = record
+            .header("Content-Length")
+            .context("warc record has no Content-Length")?
+            .parse::<u64>()
+            .context("invalid warc Content-Length")?;
+
+        // Read as it comes, a corrupt length must not allocate more than the archive holds
+        let read = (&mut self.reader)
+            .take(length)
+            .read_to_end(&mut record.block)?;
+        if read as u64 != length {
+            anyhow::bail!("archive ended inside a record block");
+        }
+
+        Ok(Some(record))
+    }
+
+    fn read_line(&mut self) -> Result<Option<String>> {
+        let mut line = vec![];
+        if self.reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = String::from_utf8_lossy(&line);
+        Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+}
+
+impl<R: BufRead> Iterator for RecordReader<R> {
+    type Item = Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::{Record, RecordReader};
+
+    fn record(uri: &str, block: &[u8]) -> Record {
+        Record {
+            version: "WARC/1.1".to_string(),
+            headers: vec![
+                ("WARC-Type".to_string(), "resource".to_string()),
+                ("WARC-Target-URI".to_string(), uri.to_string()),
+                // Rewritten from the block when serialized
+                ("Content-Length".to_string(), "0".to_string()),
+            ],
+            block: block.to_vec(),
+        }
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let mut bytes = record("http://example.com/", b"first\r\n\r\nblock").to_bytes();
+        bytes.extend(record("<http://example.com/b>", b"").to_bytes());
+
+        let records = RecordReader::new(bytes.as_slice())
+            .collect::<anyhow::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].record_type(), Some("resource"));
+        assert_eq!(records[0].header("content-length"), Some("14"));
+        assert_eq!(records[0].block, b"first\r\n\r\nblock");
+        assert_eq!(records[1].target_uri(), Some("http://example.com/b"));
+        assert!(records[1].block.is_empty());
+    }
+
+    #[test]
+    fn records_compressed_one_by_one() {
+        let mut bytes = vec![];
+        for uri in ["http://example.com/a", "http://example.com/b"] {
+            let mut encoder = GzEncoder::new(vec![], Compression::default());
+            encoder.write_all(&record(uri, b"body").to_bytes()).unwrap();
+            bytes.extend(encoder.finish().unwrap());
+        }
+
+        let reader = std::io::BufReader::new(flate2::read::MultiGzDecoder::new(bytes.as_slice()));
+        let uris = RecordReader::new(reader)
+            .map(|record| record.unwrap().target_uri().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(uris, ["http://example.com/a", "http://example.com/b"]);
+    }
+
+    #[test]
+    fn malformed_records() {
+        let not_warc = b"HTTP/1.1 200 OK\r\n\r\n";
+        assert!(RecordReader::new(&not_warc[..]).next_record().is_err());
+
+        let no_length = b"WARC/1.1\r\nWARC-Type: resource\r\n\r\n";
+        assert!(RecordReader::new(&no_length[..]).next_record().is_err());
+
+        let cut_short = b"WARC/1.1\r\nContent-Length: 10\r\n\r\nabc";
+        assert!(RecordReader::new(&cut_short[..]).next_record().is_err());
+
+        let huge = b"WARC/1.1\r\nContent-Length: 99999999999999\r\n\r\nabc";
+        assert!(RecordReader::new(&huge[..]).next_record().is_err());
+
+        assert!(RecordReader::new(&b"\r\n\r\n"[..])
+            .next_record()
+            .unwrap()
+            .is_none());
+    }
+}
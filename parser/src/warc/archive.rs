@@ -0,0 +1,432 @@
+use std::{
+    collections::HashMap,
+    error::Error as StdError,
+    fmt,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use reqwest::{Method, ResponseBuilderExt};
+use url::Url;
+
+use crate::asyncreq::CertificateInvalid;
+
+use super::{
+    writer::{
+        CERTIFICATE_CONTENT_TYPE, CERTIFICATE_INVALID_HEADER, METHOD_HEADER, TRUNCATED_HEADER,
+    },
+    CapturedAt, PeerCertificate, Record, RecordReader,
+};
+
+/// Most headers a captured response may have
+const MAX_HEADERS: usize = 256;
+
+/// Returned when replaying a url that has no response in the archive
+#[derive(Debug)]
+pub struct NotArchivedError(pub String);
+
+impl fmt::Display for NotArchivedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "url is not in the archive: {}", self.0)
+    }
+}
+
+impl StdError for NotArchivedError {}
+
+/// Returned when replaying a url whose archived response is no longer a valid response
+#[derive(Debug)]
+pub struct InvalidArchivedResponseError(pub String);
+
+impl fmt::Display for InvalidArchivedResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "archived response cannot be replayed: {}", self.0)
+    }
+}
+
+impl StdError for InvalidArchivedResponseError {}
+
+/// Http response captured in an archive, with transfer and content encodings removed
+#[derive(Debug, Clone)]
+pub struct ArchivedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, Vec<u8>)>,
+    pub body: Bytes,
+    /// Unix time of the record's `WARC-Date`
+    pub captured_at: Option<i64>,
+    /// The certificate failed verification and was accepted when recording
+    pub certificate_invalid: bool,
+}
+
+/// Responses of one or more archives, looked up by url.
+/// Every response is kept in memory. The last capture of a url wins,
+/// as it is the one a scan kept after retrying the earlier ones.
+#[derive(Debug, Default)]
+pub struct Archive {
+    responses: HashMap<String, ArchivedResponse>,
+    /// Responses to HEAD probes, replayed only for HEAD
+    heads: HashMap<String, ArchivedResponse>,
+    /// Peer certificates from metadata records, written when recording
+    certificates: HashMap<String, Vec<u8>>,
+}
+
+impl Archive {
+    pub fn open(paths: &[PathBuf]) -> Result<Self> {
+        let mut archive = Archive::default();
+        for path in paths {
+            archive.add_file(path)?;
+        }
+
+        Ok(archive)
+    }
+
+    pub fn add_file(&mut self, path: &Path) -> Result<()> {
+        for record in RecordReader::open(path)? {
+            let record = record.with_context(|| format!("invalid archive: {}", path.display()))?;
+            self.add_record(&record)
+                .with_context(|| format!("invalid archive: {}", path.display()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Indexes response and certificate records, other records are ignored
+    pub fn add_record(&mut self, record: &Record) -> Result<()> {
+        let Some(key) = record.target_uri().and_then(archive_key) else {
+            return Ok(());
+        };
+        let content_type = record.header("Content-Type").unwrap_or_default();
+
+        match record.record_type() {
+            Some("response") if content_type.starts_with("application/http") => {
+                let is_head = record.header(METHOD_HEADER) == Some("HEAD");
+                // Neither has the body its headers describe
+                let truncated = record.header(TRUNCATED_HEADER).is_some() || is_head;
+                let mut response = parse_http_response(&record.block, truncated)
+                    .with_context(|| format!("invalid response record for {key}"))?;
+                response.captured_at = record
+                    .header("WARC-Date")
+                    .and_then(|date| chrono::DateTime::parse_from_rfc3339(date).ok())
+                    .map(|date| date.timestamp());
+                response.certificate_invalid = record.header(CERTIFICATE_INVALID_HEADER).is_some();
+                match is_head {
+                    true => self.heads.insert(key, response),
+                    false => self.responses.insert(key, response),
+                };
+            }
+            Some("metadata") if content_type == CERTIFICATE_CONTENT_TYPE => {
+                self.certificates.insert(key, record.block.clone());
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.responses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.responses.is_empty()
+    }
+
+    pub fn get(&self, url: &Url) -> Option<&ArchivedResponse> {
+        self.responses.get(&archive_key(url.as_str())?)
+    }
+
+    /// Replays the captured response of a url.
+    /// A HEAD is answered with the captured HEAD, or the captured get when there is none.
+    pub fn response(&self, url: &Url, method: &Method) -> Result<reqwest::Response> {
+        let head = match method {
+            &Method::HEAD => archive_key(url.as_str()).and_then(|key| self.heads.get(&key)),
+            _ => None,
+        };
+        let archived = head
+            .or_else(|| self.get(url))
+            .ok_or_else(|| NotArchivedError(url.to_string()))?;
+
+        let mut builder = http::Response::builder()
+            .status(archived.status)
+            .url(url.clone());
+        if let Some(captured_at) = archived.captured_at {
+            builder = builder.extension(CapturedAt(captured_at));
+        }
+        if let Some(der) = self
+            .certificates
+            .get(&archive_key(url.as_str()).unwrap_or_default())
+        {
+            builder = builder.extension(PeerCertificate(der.clone()));
+        }
+        if archived.certificate_invalid {
+            builder = builder.extension(CertificateInvalid);
+        }
+        for (name, value) in &archived.headers {
+            builder = builder.header(name.as_str(), value.as_slice());
+        }
+
+        // Captured headers were valid once, but the archive may have been edited since
+        let response = builder
+            .body(archived.body.clone())
+            .map_err(|e| InvalidArchivedResponseError(format!("{url}: {e}")))?;
+        Ok(reqwest::Response::from(response))
+    }
+}
+
+/// Urls are matched without their fragment
+fn archive_key(url: &str) -> Option<String> {
+    let mut url = Url::parse(url).ok()?;
+    url.set_fragment(None);
+    Some(url.to_string())
+}
+
+/// Truncated bodies keep their original headers
+fn parse_http_response(block: &[u8], truncated: bool) -> Result<ArchivedResponse> {
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut response = httparse::Response::new(&mut headers);
+    let httparse::Status::Complete(head_length) = response.parse(block)? else {
+        anyhow::bail!("http headers are incomplete");
+    };
+
+    let status = response.code.context("http response has no status")?;
+    let mut headers = response
+        .headers
+        .iter()
+        .map(|header| (header.name.to_ascii_lowercase(), header.value.to_vec()))
+        .collect::<Vec<_>>();
+    let mut body = block[head_length..].to_vec();
+
+    // A truncated body cannot be decoded, it is replayed as captured
+    if truncated {
+        return Ok(ArchivedResponse {
+            status,
+            headers,
+            body: Bytes::from(body),
+            captured_at: None,
+            certificate_invalid: false,
+        });
+    }
+
+    if header_contains(&headers, "transfer-encoding", "chunked") {
+        body = dechunk(&body)?;
+    }
+    if header_contains(&headers, "content-encoding", "gzip") {
+        body = decode(GzDecoder::new(body.as_slice()))?;
+    } else if header_contains(&headers, "content-encoding", "deflate") {
+        body = decode(ZlibDecoder::new(body.as_slice()))?;
+    }
+
+    // The body is now stored as is
+    headers.retain(|(name, _)| {
+        !matches!(
+            name.as_str(),
+            "transfer-encoding" | "content-encoding" | "content-length"
+        )
+    });
+    headers.push((
+        "content-length".to_string(),
+        body.len().to_string().into_bytes(),
+    ));
+
+    Ok(ArchivedResponse {
+        status,
+        headers,
+        body: Bytes::from(body),
+        captured_at: None,
+        certificate_invalid: false,
+    })
+}
+
+fn header_contains(headers: &[(String, Vec<u8>)], name: &str, value: &str) -> bool {
+    headers.iter().any(|(key, val)| {
+        key == name
+            && String::from_utf8_lossy(val)
+                .to_ascii_lowercase()
+                .contains(value)
+    })
+}
+
+fn decode(mut decoder: impl Read) -> Result<Vec<u8>> {
+    let mut body = vec![];
+    decoder.read_to_end(&mut body)?;
+    Ok(body)
+}
+
+/// Removes chunked transfer encoding, trailers are dropped
+fn dechunk(mut chunked: &[u8]) -> Result<Vec<u8>> {
+    let mut body = vec![];
+    loop {
+        let httparse::Status::Complete((start, size)) = httparse::parse_chunk_size(chunked)
+            .map_err(|_| anyhow::anyhow!("invalid chunk size"))?
+        else {
+            anyhow::bail!("chunked body is incomplete");
+        };
+        if size == 0 {
+            return Ok(body);
+        }
+
+        let end = usize::try_from(size)
+            .ok()
+            .and_then(|size| start.checked_add(size))
+            .context("invalid chunk size")?;
+        let chunk = chunked
+            .get(start..end)
+            .context("chunked body is incomplete")?;
+        body.extend_from_slice(chunk);
+        if chunked.get(end..end + 2) != Some(b"\r\n") {
+            anyhow::bail!("chunk is not followed by CRLF");
+        }
+        chunked = &chunked[end + 2..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+    use url::Url;
+
+    use crate::{parser::failure::FailureStage, warc::Record};
+
+    use super::{dechunk, parse_http_response, Archive, ArchivedResponse, Method};
+
+    fn response_record(url: &str, http: &[u8]) -> Record {
+        Record {
+            version: "WARC/1.1".to_string(),
+            headers: vec![
+                ("WARC-Type".to_string(), "response".to_string()),
+                ("WARC-Target-URI".to_string(), url.to_string()),
+                (
+                    "Content-Type".to_string(),
+                    "application/http; msgtype=response".to_string(),
+                ),
+            ],
+            block: http.to_vec(),
+        }
+    }
+
+    fn header<'a>(response: &'a ArchivedResponse, name: &str) -> Option<&'a [u8]> {
+        response
+            .headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    #[test]
+    fn chunked_body() {
+        assert_eq!(
+            dechunk(b"4\r\nWiki\r\n5;ext=1\r\npedia\r\n0\r\n\r\n").unwrap(),
+            b"Wikipedia"
+        );
+        // Trailers are dropped
+        assert_eq!(
+            dechunk(b"2\r\nok\r\n0\r\nx-trailer: 1\r\n\r\n").unwrap(),
+            b"ok"
+        );
+    }
+
+    #[test]
+    fn chunked_body_must_be_well_formed() {
+        // Chunk longer than its size says
+        assert!(dechunk(b"2\r\nabc\r\n0\r\n\r\n").is_err());
+        // Chunk cut short
+        assert!(dechunk(b"5\r\nab").is_err());
+        // No last chunk
+        assert!(dechunk(b"2\r\nab\r\n").is_err());
+        assert!(dechunk(b"zz\r\nab\r\n0\r\n\r\n").is_err());
+        assert!(dechunk(b"ffffffffffffffff\r\nab\r\n0\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn gzip_and_chunked_response() {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(b"<p>hello</p>").unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let mut http = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-encoding: gzip\r\ntransfer-encoding: chunked\r\n\r\n{:x}\r\n",
+            gzipped.len()
+        )
+        .into_bytes();
+        http.extend_from_slice(&gzipped);
+        http.extend_from_slice(b"\r\n0\r\n\r\n");
+
+        let response = parse_http_response(&http, false).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(&response.body[..], b"<p>hello</p>");
+        assert_eq!(header(&response, "content-length"), Some(&b"12"[..]));
+        assert_eq!(header(&response, "content-encoding"), None);
+        assert_eq!(header(&response, "transfer-encoding"), None);
+        assert_eq!(header(&response, "content-type"), Some(&b"text/html"[..]));
+    }
+
+    #[test]
+    fn truncated_response_is_kept_as_captured() {
+        let http =
+            b"HTTP/1.1 200 OK\r\ncontent-encoding: gzip\r\ncontent-length: 9000\r\n\r\n\x1f\x8b";
+        let response = parse_http_response(http, true).unwrap();
+        assert_eq!(&response.body[..], b"\x1f\x8b");
+        assert_eq!(header(&response, "content-length"), Some(&b"9000"[..]));
+    }
+
+    #[test]
+    fn incomplete_headers_are_invalid() {
+        assert!(parse_http_response(b"HTTP/1.1 200 OK\r\ncontent-type: text/html", false).is_err());
+    }
+
+    #[test]
+    fn last_capture_wins_and_fragments_are_ignored() {
+        let mut archive = Archive::default();
+        archive
+            .add_record(&response_record(
+                "http://example.com/",
+                b"HTTP/1.1 429 Too Many Requests\r\n\r\n",
+            ))
+            .unwrap();
+        archive
+            .add_record(&response_record(
+                "<http://example.com/>",
+                b"HTTP/1.1 200 OK\r\n\r\nok",
+            ))
+            .unwrap();
+
+        assert_eq!(archive.len(), 1);
+        let url = Url::parse("http://example.com/#top").unwrap();
+        assert_eq!(archive.get(&url).unwrap().status, 200);
+    }
+
+    #[test]
+    fn missing_and_invalid_responses() {
+        let mut archive = Archive::default();
+        archive
+            .add_record(&response_record(
+                "http://example.com/",
+                b"HTTP/1.1 200 OK\r\n\r\nok",
+            ))
+            .unwrap();
+        // Header values edited to something a response cannot carry
+        archive
+            .responses
+            .get_mut("http://example.com/")
+            .unwrap()
+            .headers
+            .push(("x-edited".to_string(), b"line\nbreak".to_vec()));
+
+        let missing = archive
+            .response(
+                &Url::parse("http://example.com/missing").unwrap(),
+                &Method::GET,
+            )
+            .unwrap_err();
+        assert_eq!(FailureStage::of(&missing), FailureStage::NotArchived);
+
+        let invalid = archive
+            .response(&Url::parse("http://example.com/").unwrap(), &Method::GET)
+            .unwrap_err();
+        assert_eq!(FailureStage::of(&invalid), FailureStage::Decode);
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use flate2::{write::GzEncoder, Compression};
+use http::{HeaderMap, Method, StatusCode};
+use url::Url;
+
+use super::Record;
+
+/// Exchange to archive, the body as the caller saw it
+pub struct Capture<'a> {
+    pub method: &'a Method,
+    pub url: &'a Url,
+    pub request_headers: &'a HeaderMap,
+    pub status: StatusCode,
+    pub headers: &'a HeaderMap,
+    pub body: &'a [u8],
+    /// Why the body was cut short, as `WARC-Truncated`, its original content length is kept
+    pub truncated: Option<&'a str>,
+    pub peer_certificate: Option<&'a [u8]>,
+    /// The certificate failed verification and was accepted
+    pub certificate_invalid: bool,
+}
+
+/// Appends every fetched response to a web archive.
+/// A `.warc.gz` path compresses each record on its own, as readers expect.
+#[derive(Debug)]
+pub struct WarcWriter {
+    file: Mutex<BufWriter<File>>,
+    filepath: PathBuf,
+    gzip: bool,
+}
+
+impl WarcWriter {
+    /// Opens the archive for appending, starting with a warcinfo record
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open archive: {}", path.display()))?;
+
+        let writer = WarcWriter {
+            file: Mutex::new(BufWriter::new(file)),
+            filepath: path.to_path_buf(),
+            gzip: path.extension().is_some_and(|ext| ext == "gz"),
+        };
+        let info = format!(
+            "software: sharkalyze-parser/{}\r\nformat: WARC File Format 1.1\r\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        writer.write(&[record(
+            "warcinfo",
+            None,
+            "application/warc-fields",
+            vec![],
+            info.into_bytes(),
+        )])?;
+
+        Ok(writer)
+    }
+
+    pub fn filepath(&self) -> &PathBuf {
+        &self.filepath
+    }
+
+    /// Writes the request, response and certificate records of an exchange.
+    /// Bodies are stored without transfer encoding, so the length headers are rewritten,
+    /// unless the body was truncated or the request was a HEAD. The file is written on a blocking thread.
+    pub async fn capture(self: &Arc<Self>, capture: Capture<'_>) -> Result<()> {
+        let records = exchange_records(capture);
+        self.write_blocking(records).await
+    }
+
+    /// Writes the request of an exchange that got no response,
+    /// with a metadata record saying why
+    pub async fn capture_error(
+        self: &Arc<Self>,
+        method: &Method,
+        url: &Url,
+        request_headers: &HeaderMap,
+        error: &str,
+    ) -> Result<()> {
+        let request = record(
+            "request",
+            Some(url.as_str()),
+            "application/http; msgtype=request",
+            vec![],
+            request_block(method, url, request_headers),
+        );
+        let concurrent_to = vec![(
+            "WARC-Concurrent-To".to_string(),
+            request
+                .header("WARC-Record-ID")
+                .unwrap_or_default()
+                .to_string(),
+        )];
+        let error = format!("error: {}\r\n", error.replace(['\r', '\n'], " "));
+        let metadata = record(
+            "metadata",
+            Some(url.as_str()),
+            "application/warc-fields",
+            concurrent_to,
+            error.into_bytes(),
+        );
+        self.write_blocking(vec![request, metadata]).await
+    }
+
+    async fn write_blocking(self: &Arc<Self>, records: Vec<Record>) -> Result<()> {
+        let writer = Arc::clone(self);
+        tokio::task::spawn_blocking(move || writer.write(&records)).await?
+    }
+
+    /// Writes records together, flushed so the archive is complete if the scan stops
+    fn write(&self, records: &[Record]) -> Result<()> {
+        let mut bytes = vec![];
+        for record in records {
+            if self.gzip {
+                let mut encoder = GzEncoder::new(vec![], Compression::default());
+                encoder.write_all(&record.to_bytes())?;
+                bytes.extend(encoder.finish()?);
+            } else {
+                bytes.extend(record.to_bytes());
+            }
+        }
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&bytes)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+/// Request line and headers of an exchange
+fn request_block(method: &Method, url: &Url, request_headers: &HeaderMap) -> Vec<u8> {
+    let host = url.host_str().unwrap_or_default();
+    let path = &url[url::Position::BeforePath..url::Position::AfterQuery];
+
+    let mut request = format!("{method} {path} HTTP/1.1\r\nhost: {host}\r\n");
+    for (name, value) in request_headers {
+        request.push_str(&format!(
+            "{name}: {}\r\n",
+            String::from_utf8_lossy(value.as_bytes())
+        ));
+    }
+    request.push_str("\r\n");
+    request.into_bytes()
+}
+
+/// Response, request and certificate records of an exchange
+fn exchange_records(capture: Capture) -> Vec<Record> {
+    let target = capture.url.as_str();
+    // A HEAD has no body, its headers describe the body a get would have had
+    let is_head = capture.method == Method::HEAD;
+    let keep_length = capture.truncated.is_some() || is_head;
+
+    // Written as http/1.1 whatever was negotiated, like other archivers do
+    let mut response = format!(
+        "HTTP/1.1 {} {}\r\n",
+        capture.status.as_u16(),
+        capture.status.canonical_reason().unwrap_or_default()
+    )
+    .into_bytes();
+    for (name, value) in capture.headers {
+        if name == http::header::TRANSFER_ENCODING
+            || (name == http::header::CONTENT_LENGTH && !keep_length)
+        {
+            continue;
+        }
+        response.extend_from_slice(format!("{name}: ").as_bytes());
+        response.extend_from_slice(value.as_bytes());
+        response.extend_from_slice(b"\r\n");
+    }
+    if !keep_length {
+        response
+            .extend_from_slice(format!("content-length: {}\r\n", capture.body.len()).as_bytes());
+    }
+    response.extend_from_slice(b"\r\n");
+    response.extend_from_slice(capture.body);
+
+    let mut extra = vec![];
+    if let Some(reason) = capture.truncated {
+        extra.push((TRUNCATED_HEADER.to_string(), reason.to_string()));
+    }
+    if is_head {
+        extra.push((METHOD_HEADER.to_string(), Method::HEAD.to_string()));
+    }
+    if capture.certificate_invalid {
+        extra.push((CERTIFICATE_INVALID_HEADER.to_string(), "true".to_string()));
+    }
+    let response = record(
+        "response",
+        Some(target),
+        "application/http; msgtype=response",
+        extra,
+        response,
+    );
+    let concurrent_to = vec![(
+        "WARC-Concurrent-To".to_string(),
+        response
+            .header("WARC-Record-ID")
+            .unwrap_or_default()
+            .to_string(),
+    )];
+    let request = record(
+        "request",
+        Some(target),
+        "application/http; msgtype=request",
+        concurrent_to.clone(),
+        request_block(capture.method, capture.url, capture.request_headers),
+    );
+
+    let mut records = vec![response, request];
+    if let Some(der) = capture.peer_certificate {
+        records.push(record(
+            "metadata",
+            Some(target),
+            CERTIFICATE_CONTENT_TYPE,
+            concurrent_to,
+            der.to_vec(),
+        ));
+    }
+    records
+}
+
+/// Marks response records whose body was cut short
+pub const TRUNCATED_HEADER: &str = "WARC-Truncated";
+
+/// Marks response records answering a HEAD, so they do not replay as a get.
+/// Not a standard field, other readers ignore it.
+pub const METHOD_HEADER: &str = "WARC-Sharkalyze-Method";
+
+/// Marks response records whose certificate failed verification and was accepted.
+/// Not a standard field, other readers ignore it.
+pub const CERTIFICATE_INVALID_HEADER: &str = "WARC-Sharkalyze-Certificate-Invalid";
+
+/// Content type of metadata records holding the peer certificate (DER)
+pub const CERTIFICATE_CONTENT_TYPE: &str = "application/pkix-cert";
+
+fn record(
+    record_type: &str,
+    target: Option<&str>,
+    content_type: &str,
+    extra: Vec<(String, String)>,
+    block: Vec<u8>,
+) -> Record {
+    let mut headers = vec![
+        ("WARC-Type".to_string(), record_type.to_string()),
+        (
+            "WARC-Record-ID".to_string(),
+            format!("<urn:uuid:{}>", uuid::Uuid::new_v4()),
+        ),
+        (
+            "WARC-Date".to_string(),
+            Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        ),
+    ];
+    if let Some(target) = target {
+        headers.push(("WARC-Target-URI".to_string(), target.to_string()));
+    }
+    headers.push(("Content-Type".to_string(), content_type.to_string()));
+    headers.extend(extra);
+
+    Record {
+        version: "WARC/1.1".to_string(),
+        headers,
+        block,
+    }
+}
@@ -6,13 +6,110 @@ use std::{
 use anyhow::{Context, Result};
 
 use lazy_static::lazy_static;
+use publicsuffix::Psl;
 use regex::Regex;
 use url::Url;
 
+pub mod lexical;
+
 lazy_static! {
     pub static ref URL_REGEXP: Regex = Regex::new(r"^https?://").unwrap();
-    pub static ref SAMESITE_URL_REGEXP: Regex = Regex::new(r#"^/?[^:?#]+"#).unwrap();
-    pub static ref HTML_URL: Regex = Regex::new(r#"(?:href|src)\s*=\s*["']([^"']+)["']"#).unwrap();
+    /// Snapshot of https://publicsuffix.org/list/public_suffix_list.dat
+    static ref PUBLIC_SUFFIXES: publicsuffix::List =
+        include_str!("../data/public_suffix_list.dat").parse().unwrap();
+}
+
+/// Hosts of well known url shorteners
+const URL_SHORTENERS: &[&str] = &[
+    "bit.ly",
+    "bitly.com",
+    "t.co",
+    "tinyurl.com",
+    "goo.gl",
+    "ow.ly",
+    "is.gd",
+    "buff.ly",
+    "rebrand.ly",
+    "cutt.ly",
+    "shorturl.at",
+    "rb.gy",
+    "t.ly",
+    "tiny.cc",
+    "lnkd.in",
+    "s.id",
+    "v.gd",
+    "qrco.de",
+    "qr.io",
+    "bl.ink",
+    "short.io",
+    "x.co",
+    "soo.gd",
+    "clck.ru",
+    "u.to",
+];
+
+/// Registrable domain (eTLD+1) of the url, from the public suffix list.
+/// Hosts that are a public suffix themselves and ip addresses are returned as is.
+pub fn registrable_domain(url: &Url) -> Option<String> {
+    match url.host()? {
+        url::Host::Domain(domain) => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            Some(
+                PUBLIC_SUFFIXES
+                    .domain(domain.as_bytes())
+                    .and_then(|registrable| std::str::from_utf8(registrable.as_bytes()).ok())
+                    .map_or_else(|| domain.clone(), str::to_string),
+            )
+        }
+        host => Some(host.to_string()),
+    }
+}
+
+/// How a link relates to the page it was found on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkRelation {
+    /// Same scheme, host and port
+    SameOrigin,
+    /// Another origin on the same registrable domain, such as a subdomain
+    SameSite,
+    ThirdParty,
+}
+
+pub fn link_relation(link: &Url, page: &Url) -> LinkRelation {
+    if link.origin() == page.origin() {
+        LinkRelation::SameOrigin
+    } else if registrable_domain(link).is_some()
+        && registrable_domain(link) == registrable_domain(page)
+    {
+        LinkRelation::SameSite
+    } else {
+        LinkRelation::ThirdParty
+    }
+}
+
+/// Extensions of images, media, archives, documents, fonts and executables,
+/// which are never html and are probed for their headers only
+const BINARY_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "webp", "avif", "bmp", "ico", "svg", "tif", "tiff", "mp4", "webm",
+    "mov", "avi", "mkv", "m4v", "mp3", "wav", "ogg", "flac", "m4a", "aac", "zip", "rar", "7z",
+    "tar", "gz", "tgz", "bz2", "xz", "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "woff",
+    "woff2", "ttf", "otf", "eot", "exe", "msi", "dmg", "apk", "iso", "bin",
+];
+
+/// Whether the url's path ends in the extension of a non-html resource
+pub fn has_binary_extension(url: &Url) -> bool {
+    url.path()
+        .rsplit_once('.')
+        .filter(|(_, ext)| !ext.contains('/'))
+        .is_some_and(|(_, ext)| BINARY_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Whether the url is on a known url shortener
+pub fn is_url_shortener(url: &Url) -> bool {
+    url.host_str().is_some_and(|host| {
+        let host = host.trim_start_matches("www.").to_ascii_lowercase();
+        URL_SHORTENERS.contains(&host.as_str())
+    })
 }
 
 pub fn parse_url(url: &str) -> Result<Url> {
@@ -32,6 +129,24 @@ fn parse_from_file(path: &str) -> Result<Vec<Result<Url>>> {
         .collect())
 }
 
+/// Parses every line of an input, keeping the raw line for reporting
+pub fn parse_input_lines(url_or_path: &str) -> Result<Vec<(String, Result<Url>)>> {
+    if let Ok(url) = parse_url(url_or_path) {
+        return Ok(vec![(url_or_path.to_string(), Ok(url))]);
+    }
+
+    let file = std::fs::File::open(url_or_path)
+        .with_context(|| format!("failed to open file: {url_or_path}"))?;
+    BufReader::new(file)
+        .lines()
+        .map(|ln| {
+            let ln = ln?;
+            let parsed = parse_url(&ln);
+            Ok((ln, parsed))
+        })
+        .collect()
+}
+
 pub fn get_urls(url_or_path: &str) -> Result<HashSet<Url>> {
     match parse_url(url_or_path) {
         Ok(url) => Ok(HashSet::from([url])),
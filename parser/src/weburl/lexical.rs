@@ -0,0 +1,67 @@
+use url::{Host, Url};
+
+use crate::parser::vector::{format_bool, UrlLexical};
+
+use super::registrable_domain;
+
+/// Words phishing urls use to look legitimate
+const SUSPICIOUS_TOKENS: &[&str] = &[
+    "login", "signin", "verify", "secure", "account", "pay", "update", "confirm", "bank",
+    "password", "wallet", "billing",
+];
+
+/// Characters that only separate the parts of a url
+const SEPARATORS: &[char] = &[':', '/', '.'];
+
+/// Lexical features of a url, all zero when it cannot be parsed
+pub fn lexical_features(url: &str) -> UrlLexical {
+    let mut lexical = UrlLexical::default();
+    let length = url.chars().count();
+    if length == 0 {
+        return lexical;
+    }
+
+    lexical.url_length = length;
+    lexical.url_digit_ratio =
+        url.chars().filter(char::is_ascii_digit).count() as f32 / length as f32;
+    lexical.url_special_char_ratio = url
+        .chars()
+        .filter(|c| !c.is_alphanumeric() && !SEPARATORS.contains(c))
+        .count() as f32
+        / length as f32;
+    lexical.url_has_at_symbol = format_bool(url.contains('@'));
+
+    let lowercase = url.to_lowercase();
+    lexical.suspicious_token_count = SUSPICIOUS_TOKENS
+        .iter()
+        .filter(|token| lowercase.contains(*token))
+        .count();
+
+    let Ok(parsed) = Url::parse(url) else {
+        return lexical;
+    };
+
+    lexical.path_depth = parsed
+        .path_segments()
+        .map_or(0, |segments| segments.filter(|s| !s.is_empty()).count());
+    lexical.query_param_count = parsed.query_pairs().count();
+    // Default ports are dropped when parsing
+    lexical.has_non_standard_port = format_bool(parsed.port().is_some());
+
+    match parsed.host() {
+        Some(Host::Domain(domain)) => {
+            let domain = domain.trim_end_matches('.');
+            let labels = domain.split('.').collect::<Vec<_>>();
+            let registrable_labels = registrable_domain(&parsed)
+                .map_or(labels.len(), |registrable| registrable.split('.').count());
+
+            lexical.subdomain_depth = labels.len().saturating_sub(registrable_labels);
+            lexical.punycode_label_count = labels.iter().filter(|l| l.starts_with("xn--")).count();
+            lexical.tld = labels.last().unwrap_or(&"").to_ascii_lowercase();
+        }
+        Some(Host::Ipv4(_) | Host::Ipv6(_)) => lexical.host_is_ip = 1,
+        None => {}
+    }
+
+    lexical
+}
@@ -1,7 +1,34 @@
+import json
+import os
+import urllib.error
+import urllib.request
+
 from flask import current_app as app, request, jsonify
 
 
 BASE_URL = "/api/v1"
+PARSER_URL = os.environ.get("PARSER_URL", "http://127.0.0.1:8081/api/v1")
+# Seconds to wait on the parser before giving up on it
+PARSER_TIMEOUT = float(os.environ.get("PARSER_TIMEOUT", "60"))
+
+
+def analyse_url(url: str) -> tuple[dict, int]:
+    """Asks the parser service for the vector of a url"""
+    parser_request = urllib.request.Request(
+        f"{PARSER_URL}/analyze",
+        data=json.dumps({"url": url}).encode(),
+        headers={"Content-Type": "application/json"},
+        method="POST",
+    )
+
+    try:
+        with urllib.request.urlopen(parser_request, timeout=PARSER_TIMEOUT) as response:
+            return json.load(response), response.status
+    except urllib.error.HTTPError as e:
+        try:
+            return json.load(e), e.code
+        except ValueError:
+            return {"error": e.reason}, e.code
 
 
 @app.route(f"{BASE_URL}/healthcheck")
@@ -13,5 +40,18 @@ def v1_healthcheck():
 @app.route(f"{BASE_URL}/qr-analyse", methods=["POST"])
 def qrAnalyse():
     request_data = request.get_json(force=True)
-    response_data = {"message": "Processing complete", "data": request_data}
+    if not isinstance(request_data, str):
+        return jsonify({"message": "Expected the scanned url"}), 400
+
+    try:
+        vector, status = analyse_url(request_data)
+    except urllib.error.URLError:
+        return jsonify({"message": "Parser is unavailable"}), 503
+    except TimeoutError:
+        return jsonify({"message": "Parser timed out"}), 504
+
+    if status != 200:
+        return jsonify({"message": "Failed to analyse url", "data": vector}), 422
+
+    response_data = {"message": "Processing complete", "data": vector}
     return jsonify(response_data), 201